@@ -0,0 +1,117 @@
+use pagurus::image::Rgba;
+use pixcil::app::App;
+use pixcil::marker::{Mark, MouseState, fill::FillMarker};
+use pixcil::model::Models;
+use pixcil::pixel::{Pixel, PixelPosition, PixelRegion, PixelSize};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const SIZE: i16 = 512;
+const FRAMES: i16 = 4;
+
+fn main() {
+    let region = PixelRegion::from_position_and_size(
+        PixelPosition::from_xy(0, 0),
+        PixelSize::from_wh(SIZE as u16, SIZE as u16),
+    );
+
+    bench("draw (512x512 x 4 frames)", || {
+        let mut models = Models::default();
+        draw_frames(&mut models);
+        black_box(models);
+    });
+
+    bench("draw (1000 small strokes)", || {
+        let mut models = Models::default();
+        let config = models.config.clone();
+        for i in 0..1000 {
+            let pixels = (0..16).map(|j| {
+                let position = PixelPosition::from_xy((i * 7 + j) % SIZE, (i * 3) % SIZE);
+                Pixel::new(position, color(i))
+            });
+            models
+                .pixel_canvas
+                .draw_pixels(&config, pixels)
+                .expect("draw");
+        }
+        black_box(models);
+    });
+
+    let mut app = App::new().expect("app");
+    draw_frames(app.models_mut());
+    bench("fill (512x512 area)", || {
+        let mut marker = FillMarker::default();
+        marker.mark(&app, PixelPosition::from_xy(1, 1), MouseState::Clicked);
+        black_box(marker.marked_pixels(&app).count());
+    });
+
+    bench("replace_color", || {
+        let models = app.models_mut();
+        models
+            .pixel_canvas
+            .replace_color(&models.config, color(0), color(1))
+            .expect("replace");
+        models
+            .pixel_canvas
+            .undo_command(&mut models.config)
+            .expect("undo");
+    });
+
+    bench("full-frame render (512x512)", || {
+        let models = app.models();
+        let count = models
+            .pixel_canvas
+            .get_pixels(&models.config, region)
+            .count();
+        black_box(count);
+    });
+
+    let models = app.models_mut();
+    models
+        .pixel_canvas
+        .cache_composited_pixels(&models.config, region);
+    bench("full-frame render (cached)", || {
+        let models = app.models();
+        let count = models
+            .pixel_canvas
+            .get_pixels(&models.config, region)
+            .count();
+        black_box(count);
+    });
+}
+
+fn color(i: i16) -> Rgba {
+    Rgba::new((i % 256) as u8, 100, 200, 255)
+}
+
+fn draw_frames(models: &mut Models) {
+    let config = models.config.clone();
+    for frame in 0..FRAMES {
+        let pixels = (0..SIZE).flat_map(move |y| {
+            (0..SIZE).map(move |x| {
+                let position = PixelPosition::from_xy(frame * SIZE + x, y);
+                Pixel::new(position, color(frame))
+            })
+        });
+        models
+            .pixel_canvas
+            .draw_pixels(&config, pixels)
+            .expect("draw");
+    }
+}
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    f();
+
+    let mut iterations = 0;
+    let start = Instant::now();
+    while iterations < 3 || start.elapsed() < Duration::from_secs(1) {
+        f();
+        iterations += 1;
+    }
+    let elapsed = start.elapsed() / iterations;
+    println!(
+        "{name:<32} {:>10.3} ms/iter",
+        elapsed.as_secs_f64() * 1000.0
+    );
+}
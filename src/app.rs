@@ -1,5 +1,9 @@
 use crate::{
-    asset::Assets, event::InputId, io::IoRequest, model::Models, pixel::PixelPosition,
+    asset::Assets,
+    event::InputId,
+    io::IoRequest,
+    model::{Models, SpriteSheetOptions},
+    pixel::PixelPosition,
     window::Window,
 };
 use orfail::OrFail;
@@ -26,6 +30,7 @@ pub struct App {
     pending_timeouts: Vec<(TimeoutTag, Duration)>,
     next_input_id: InputId,
     pub runtime_options: RuntimeOptions,
+    pub sprite_sheet_options: SpriteSheetOptions,
 }
 
 impl App {
@@ -40,6 +45,7 @@ impl App {
             pending_timeouts: Vec::new(),
             next_input_id: InputId::default(),
             runtime_options: RuntimeOptions::default(),
+            sprite_sheet_options: SpriteSheetOptions::default(),
         })
     }
 
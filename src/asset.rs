@@ -60,6 +60,8 @@ impl Assets {
             IconId::PenLine => &self.icons.pen_line,
             IconId::PenRectangle => &self.icons.pen_rectangle,
             IconId::PenCircle => &self.icons.pen_circle,
+            IconId::PenFilledRectangle => &self.icons.pen_filled_rectangle,
+            IconId::PenFilledCircle => &self.icons.pen_filled_circle,
             IconId::Bucket => &self.icons.bucket,
             IconId::ScissorRectangle => &self.icons.scissor_rectangle,
             IconId::ScissorLasso => &self.icons.scissor_lasso,
@@ -80,6 +82,42 @@ impl Assets {
             IconId::Double => &self.icons.double,
             IconId::UnitFrame => &self.icons.unit_frame,
             IconId::UnitPixel => &self.icons.unit_pixel,
+            IconId::PaletteAdd => &self.icons.palette_add,
+            IconId::PaletteRemove => &self.icons.palette_remove,
+            IconId::PaletteLoad => &self.icons.palette_load,
+            IconId::PaletteSave => &self.icons.palette_save,
+            IconId::History => &self.icons.history,
+            IconId::FillGlobal => &self.icons.fill_global,
+            IconId::FillConnect4 => &self.icons.fill_connect4,
+            IconId::FillConnect8 => &self.icons.fill_connect8,
+            IconId::FillBoundNone => &self.icons.fill_bound_none,
+            IconId::FillBoundFrame => &self.icons.fill_bound_frame,
+            IconId::FillBoundSelection => &self.icons.fill_bound_selection,
+            IconId::Gradient => &self.icons.gradient,
+            IconId::DitherBayer2 => &self.icons.dither_bayer2,
+            IconId::DitherBayer4 => &self.icons.dither_bayer4,
+            IconId::DitherBayer8 => &self.icons.dither_bayer8,
+            IconId::DitherChecker => &self.icons.dither_checker,
+            IconId::DitherCustom => &self.icons.dither_custom,
+            IconId::BrushSquare => &self.icons.brush_square,
+            IconId::BrushRound => &self.icons.brush_round,
+            IconId::BrushCustom => &self.icons.brush_custom,
+            IconId::SymmetryNone => &self.icons.symmetry_none,
+            IconId::SymmetryHorizontal => &self.icons.symmetry_horizontal,
+            IconId::SymmetryVertical => &self.icons.symmetry_vertical,
+            IconId::SymmetryFourWay => &self.icons.symmetry_four_way,
+            IconId::SymmetryAxis => &self.icons.symmetry_axis,
+            IconId::FrameInsert => &self.icons.frame_insert,
+            IconId::FrameDuplicate => &self.icons.frame_duplicate,
+            IconId::FrameDelete => &self.icons.frame_delete,
+            IconId::FrameMoveLeft => &self.icons.frame_move_left,
+            IconId::FrameMoveRight => &self.icons.frame_move_right,
+            IconId::LayerInsert => &self.icons.layer_insert,
+            IconId::LayerDuplicate => &self.icons.layer_duplicate,
+            IconId::LayerDelete => &self.icons.layer_delete,
+            IconId::LayerMoveUp => &self.icons.layer_move_up,
+            IconId::LayerMoveDown => &self.icons.layer_move_down,
+            IconId::LayerMergeDown => &self.icons.layer_merge_down,
         }
     }
 
@@ -114,6 +152,8 @@ pub enum IconId {
     PenLine,
     PenRectangle,
     PenCircle,
+    PenFilledRectangle,
+    PenFilledCircle,
     Bucket,
     ScissorRectangle,
     ScissorLasso,
@@ -134,6 +174,42 @@ pub enum IconId {
     Double,
     UnitFrame,
     UnitPixel,
+    PaletteAdd,
+    PaletteRemove,
+    PaletteLoad,
+    PaletteSave,
+    History,
+    FillGlobal,
+    FillConnect4,
+    FillConnect8,
+    FillBoundNone,
+    FillBoundFrame,
+    FillBoundSelection,
+    Gradient,
+    DitherBayer2,
+    DitherBayer4,
+    DitherBayer8,
+    DitherChecker,
+    DitherCustom,
+    BrushSquare,
+    BrushRound,
+    BrushCustom,
+    SymmetryNone,
+    SymmetryHorizontal,
+    SymmetryVertical,
+    SymmetryFourWay,
+    SymmetryAxis,
+    FrameInsert,
+    FrameDuplicate,
+    FrameDelete,
+    FrameMoveLeft,
+    FrameMoveRight,
+    LayerInsert,
+    LayerDuplicate,
+    LayerDelete,
+    LayerMoveUp,
+    LayerMoveDown,
+    LayerMergeDown,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -283,6 +359,8 @@ pub struct Icons {
     pub pen_line: Sprite,
     pub pen_rectangle: Sprite,
     pub pen_circle: Sprite,
+    pub pen_filled_rectangle: Sprite,
+    pub pen_filled_circle: Sprite,
     pub bucket: Sprite,
     pub scissor_rectangle: Sprite,
     pub scissor_lasso: Sprite,
@@ -303,6 +381,42 @@ pub struct Icons {
     pub double: Sprite,
     pub unit_frame: Sprite,
     pub unit_pixel: Sprite,
+    pub palette_add: Sprite,
+    pub palette_remove: Sprite,
+    pub palette_load: Sprite,
+    pub palette_save: Sprite,
+    pub history: Sprite,
+    pub fill_global: Sprite,
+    pub fill_connect4: Sprite,
+    pub fill_connect8: Sprite,
+    pub fill_bound_none: Sprite,
+    pub fill_bound_frame: Sprite,
+    pub fill_bound_selection: Sprite,
+    pub gradient: Sprite,
+    pub dither_bayer2: Sprite,
+    pub dither_bayer4: Sprite,
+    pub dither_bayer8: Sprite,
+    pub dither_checker: Sprite,
+    pub dither_custom: Sprite,
+    pub brush_square: Sprite,
+    pub brush_round: Sprite,
+    pub brush_custom: Sprite,
+    pub symmetry_none: Sprite,
+    pub symmetry_horizontal: Sprite,
+    pub symmetry_vertical: Sprite,
+    pub symmetry_four_way: Sprite,
+    pub symmetry_axis: Sprite,
+    pub frame_insert: Sprite,
+    pub frame_duplicate: Sprite,
+    pub frame_delete: Sprite,
+    pub frame_move_left: Sprite,
+    pub frame_move_right: Sprite,
+    pub layer_insert: Sprite,
+    pub layer_duplicate: Sprite,
+    pub layer_delete: Sprite,
+    pub layer_move_up: Sprite,
+    pub layer_move_down: Sprite,
+    pub layer_merge_down: Sprite,
 }
 
 impl Icons {
@@ -313,6 +427,14 @@ impl Icons {
         let pixel_size_op =
             decode_sprite(include_bytes!("../assets/icon-pixel-size-op.png")).or_fail()?;
         let middle_block = Size::from_wh(48, 20).to_region();
+        let palette_op =
+            decode_sprite(include_bytes!("../assets/icon-palette-op.png")).or_fail()?;
+        let fill_mode = decode_sprite(include_bytes!("../assets/icon-fill-mode.png")).or_fail()?;
+        let gradient = decode_sprite(include_bytes!("../assets/icon-gradient.png")).or_fail()?;
+        let brush = decode_sprite(include_bytes!("../assets/icon-brush.png")).or_fail()?;
+        let symmetry = decode_sprite(include_bytes!("../assets/icon-symmetry.png")).or_fail()?;
+        let frame_op = decode_sprite(include_bytes!("../assets/icon-frame-op.png")).or_fail()?;
+        let layer_op = decode_sprite(include_bytes!("../assets/icon-layer-op.png")).or_fail()?;
         Ok(Self {
             undo: decode_sprite(include_bytes!("../assets/icon-undo.png")).or_fail()?,
             redo: decode_sprite(include_bytes!("../assets/icon-redo.png")).or_fail()?,
@@ -333,6 +455,14 @@ impl Icons {
             pen_rectangle: decode_sprite(include_bytes!("../assets/icon-pen-rectangle.png"))
                 .or_fail()?,
             pen_circle: decode_sprite(include_bytes!("../assets/icon-pen-circle.png")).or_fail()?,
+            pen_filled_rectangle: decode_sprite(include_bytes!(
+                "../assets/icon-pen-rectangle-filled.png"
+            ))
+            .or_fail()?,
+            pen_filled_circle: decode_sprite(include_bytes!(
+                "../assets/icon-pen-circle-filled.png"
+            ))
+            .or_fail()?,
             bucket: decode_sprite(include_bytes!("../assets/icon-bucket.png")).or_fail()?,
             scissor_rectangle: decode_sprite(include_bytes!(
                 "../assets/icon-scissor-rectangle.png"
@@ -358,6 +488,42 @@ impl Icons {
             double: pixel_size_op.clip(middle_block.shift_y(1)).or_fail()?,
             unit_frame: pixel_size_op.clip(middle_block.shift_y(2)).or_fail()?,
             unit_pixel: pixel_size_op.clip(middle_block.shift_y(3)).or_fail()?,
+            palette_add: palette_op.clip(middle_block).or_fail()?,
+            palette_remove: palette_op.clip(middle_block.shift_y(1)).or_fail()?,
+            palette_load: palette_op.clip(middle_block.shift_y(2)).or_fail()?,
+            palette_save: palette_op.clip(middle_block.shift_y(3)).or_fail()?,
+            history: decode_sprite(include_bytes!("../assets/icon-history.png")).or_fail()?,
+            fill_global: fill_mode.clip(block).or_fail()?,
+            fill_connect4: fill_mode.clip(block.shift_x(1)).or_fail()?,
+            fill_connect8: fill_mode.clip(block.shift_x(2)).or_fail()?,
+            fill_bound_none: fill_mode.clip(block.shift_x(3)).or_fail()?,
+            fill_bound_frame: fill_mode.clip(block.shift_x(4)).or_fail()?,
+            fill_bound_selection: fill_mode.clip(block.shift_x(5)).or_fail()?,
+            gradient: gradient.clip(block).or_fail()?,
+            dither_bayer2: gradient.clip(block.shift_x(1)).or_fail()?,
+            dither_bayer4: gradient.clip(block.shift_x(2)).or_fail()?,
+            dither_bayer8: gradient.clip(block.shift_x(3)).or_fail()?,
+            dither_checker: gradient.clip(block.shift_x(4)).or_fail()?,
+            dither_custom: gradient.clip(block.shift_x(5)).or_fail()?,
+            brush_square: brush.clip(block).or_fail()?,
+            brush_round: brush.clip(block.shift_x(1)).or_fail()?,
+            brush_custom: brush.clip(block.shift_x(2)).or_fail()?,
+            symmetry_none: symmetry.clip(block).or_fail()?,
+            symmetry_horizontal: symmetry.clip(block.shift_x(1)).or_fail()?,
+            symmetry_vertical: symmetry.clip(block.shift_x(2)).or_fail()?,
+            symmetry_four_way: symmetry.clip(block.shift_x(3)).or_fail()?,
+            symmetry_axis: symmetry.clip(block.shift_x(4)).or_fail()?,
+            frame_insert: frame_op.clip(block).or_fail()?,
+            frame_duplicate: frame_op.clip(block.shift_x(1)).or_fail()?,
+            frame_delete: frame_op.clip(block.shift_x(2)).or_fail()?,
+            frame_move_left: frame_op.clip(block.shift_x(3)).or_fail()?,
+            frame_move_right: frame_op.clip(block.shift_x(4)).or_fail()?,
+            layer_insert: layer_op.clip(block).or_fail()?,
+            layer_duplicate: layer_op.clip(block.shift_x(1)).or_fail()?,
+            layer_delete: layer_op.clip(block.shift_x(2)).or_fail()?,
+            layer_move_up: layer_op.clip(block.shift_x(3)).or_fail()?,
+            layer_move_down: layer_op.clip(block.shift_x(4)).or_fail()?,
+            layer_merge_down: layer_op.clip(block.shift_x(5)).or_fail()?,
         })
     }
 }
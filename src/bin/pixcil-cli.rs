@@ -0,0 +1,117 @@
+use orfail::{Failure, OrFail, Result};
+use pixcil::model::{Models, SpriteSheetOptions};
+use pixcil::palette::{PaletteFormat, format_palette, parse_palette};
+use pixcil::pixel::PixelSize;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "\
+Usage: pixcil-cli <COMMAND> [ARGS]
+
+Commands:
+  export-sprite-sheet <WORKSPACE_PNG> <OUTPUT_PNG>
+      Export the animation frames as a horizontal sprite sheet
+  export-frames <WORKSPACE_PNG> <OUTPUT_DIR>
+      Export each animation frame as `frame-<N>.png`
+  export-layers <WORKSPACE_PNG> <OUTPUT_DIR>
+      Export each layer (without compositing) as `layer-<N>.png`
+  import-sprite-sheet <SHEET_PNG> <SIZE> <OUTPUT_PNG> [--grid] [--layers]
+      Create a workspace by slicing a sprite sheet into cells of SIZE (e.g. `16x16`).
+      With `--grid`, SIZE is the number of columns and rows (e.g. `4x2`).
+      With `--layers`, rows are mapped to layers instead of extra animation frames.
+  import-palette <WORKSPACE_PNG> <PALETTE_FILE> <OUTPUT_PNG>
+      Replace the workspace palette with the colors in a GPL, JASC-PAL, Paint.NET or HEX file
+  export-palette <WORKSPACE_PNG> <PALETTE_FILE>
+      Write the workspace palette (the format is chosen by the file extension)
+  dump-config <WORKSPACE_PNG>
+      Print the workspace settings as JSON
+";
+
+fn main() -> Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(|a| a.as_str()).collect::<Vec<_>>();
+    match args.as_slice() {
+        ["export-sprite-sheet", workspace, output] => {
+            let mut models = load_workspace(workspace).or_fail()?;
+            models.config.apng = Some(false);
+            let png = models.to_png().or_fail()?;
+            std::fs::write(output, png).or_fail()?;
+        }
+        ["export-frames", workspace, output_dir] => {
+            let models = load_workspace(workspace).or_fail()?;
+            let output_dir = create_dir(output_dir).or_fail()?;
+            for frame in 0..models.config.animation.enabled_frame_count() {
+                let png = models.frame_to_png(frame).or_fail()?;
+                let path = output_dir.join(format!("frame-{frame}.png"));
+                std::fs::write(path, png).or_fail()?;
+            }
+        }
+        ["export-layers", workspace, output_dir] => {
+            let models = load_workspace(workspace).or_fail()?;
+            let output_dir = create_dir(output_dir).or_fail()?;
+            for layer in 0..models.config.layer.enabled_count() {
+                let png = models.layer_to_png(layer).or_fail()?;
+                let path = output_dir.join(format!("layer-{layer}.png"));
+                std::fs::write(path, png).or_fail()?;
+            }
+        }
+        ["import-sprite-sheet", sheet, size, output, flags @ ..] => {
+            let mut options = SpriteSheetOptions {
+                size: size.parse::<PixelSize>().or_fail()?,
+                ..Default::default()
+            };
+            for flag in flags {
+                match *flag {
+                    "--grid" => options.grid = true,
+                    "--layers" => options.rows_as_layers = true,
+                    _ => return Err(Failure::new(format!("unknown option: {flag:?}"))),
+                }
+            }
+            let data =
+                std::fs::read(sheet).or_fail_with(|e| format!("failed to read {sheet:?}: {e}"))?;
+            let models = Models::from_sprite_sheet(&data, options).or_fail()?;
+            std::fs::write(output, models.to_png().or_fail()?).or_fail()?;
+        }
+        ["import-palette", workspace, palette, output] => {
+            let mut models = load_workspace(workspace).or_fail()?;
+            let text = std::fs::read_to_string(palette)
+                .or_fail_with(|e| format!("failed to read {palette:?}: {e}"))?;
+            let colors = parse_palette(&text).or_fail()?;
+            models.config.palette.set_colors(colors);
+            std::fs::write(output, models.to_png().or_fail()?).or_fail()?;
+        }
+        ["export-palette", workspace, palette] => {
+            let models = load_workspace(workspace).or_fail()?;
+            let extension = Path::new(palette)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default();
+            let format = PaletteFormat::from_extension(extension).or_fail()?;
+            let text = format_palette(models.config.palette.colors(), format);
+            std::fs::write(palette, text).or_fail()?;
+        }
+        ["dump-config", workspace] => {
+            let models = load_workspace(workspace).or_fail()?;
+            let json = serde_json::to_string_pretty(&models.config).or_fail()?;
+            println!("{json}");
+        }
+        ["-h" | "--help" | "help"] => {
+            print!("{USAGE}");
+        }
+        _ => {
+            eprint!("{USAGE}");
+            return Err(Failure::new("invalid arguments"));
+        }
+    }
+    Ok(())
+}
+
+fn load_workspace(path: &str) -> Result<Models> {
+    let data = std::fs::read(path).or_fail_with(|e| format!("failed to read {path:?}: {e}"))?;
+    Models::from_png(&data).or_fail_with(|e| format!("failed to load {path:?}: {e}"))
+}
+
+fn create_dir(path: &str) -> Result<PathBuf> {
+    let path = Path::new(path);
+    std::fs::create_dir_all(path).or_fail()?;
+    Ok(path.to_path_buf())
+}
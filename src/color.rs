@@ -24,6 +24,8 @@ pub const SILHOUETTE: Color = rgb(0, 0, 0);
 pub const FRAME_EDGE: Color = rgb(160, 160, 160);
 pub const CURRENT_FRAME_EDGE: Color = rgb(80, 80, 80);
 
+pub const SYMMETRY_AXIS: Color = rgba(0, 120, 255, 200);
+
 const fn rgb(r: u8, g: u8, b: u8) -> Color {
     Color::rgb(r, g, b)
 }
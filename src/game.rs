@@ -5,6 +5,7 @@ use crate::{
     event::Event,
     io::Input,
     model::Models,
+    palette::{PaletteFormat, format_palette, parse_palette},
     window::{Window, main::MainWindow},
 };
 use orfail::OrFail;
@@ -164,6 +165,11 @@ impl<S: System> Game<S> for PixcilGame {
                 let data = app.models().to_png().or_fail()?;
                 Ok(data)
             }
+            "paletteGpl" => {
+                let app = self.app.as_ref().or_fail()?;
+                let colors = app.models().config.palette.colors();
+                Ok(format_palette(colors, PaletteFormat::Gpl).into_bytes())
+            }
             "stateVersion" => {
                 let app = self.app.as_ref().or_fail()?;
                 let version = app.models().pixel_canvas.state_version();
@@ -193,6 +199,27 @@ impl<S: System> Game<S> for PixcilGame {
 
                 Ok(())
             }
+            "importSpriteSheet" => {
+                let app = self.app.as_mut().or_fail()?;
+                let options = app.sprite_sheet_options;
+                *app.models_mut() = Models::from_sprite_sheet(data, options).or_fail()?;
+                app.request_redraw(app.screen_size().to_region());
+                self.handle_pixcil_event(system, Some(Event::Noop))
+                    .or_fail()?;
+
+                Ok(())
+            }
+            "importPalette" => {
+                let text = std::str::from_utf8(data).or_fail()?;
+                let colors = parse_palette(text).or_fail()?;
+                let app = self.app.as_mut().or_fail()?;
+                app.models_mut().config.palette.set_colors(colors);
+                app.request_redraw(app.screen_size().to_region());
+                self.handle_pixcil_event(system, Some(Event::Noop))
+                    .or_fail()?;
+
+                Ok(())
+            }
             "disableSaveWorkspaceButton" => {
                 let app = self.app.as_mut().or_fail()?;
                 app.runtime_options.disable_save_workspace_button = true;
@@ -202,10 +229,10 @@ impl<S: System> Game<S> for PixcilGame {
                 let mut pointer_event: PointerEvent = serde_json::from_slice(data).or_fail()?;
                 let last_pointer_event = self.last_pointer_event;
                 self.last_pointer_event = Some(pointer_event);
-                if let Some(last) = last_pointer_event {
-                    if last.is_duplicate(pointer_event) {
-                        return Ok(());
-                    }
+                if let Some(last) = last_pointer_event
+                    && last.is_duplicate(pointer_event)
+                {
+                    return Ok(());
                 }
 
                 let pagurus_event = PagurusEvent::Mouse(pointer_event.to_mouse_event());
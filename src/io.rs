@@ -6,6 +6,9 @@ pub enum IoRequest {
     SaveWorkspace,
     LoadWorkspace,
     ImportImage,
+    ImportSpriteSheet,
+    ImportPalette,
+    ExportPalette,
     InputNumber { id: InputId },
     InputSize { id: InputId },
     Vibrate,
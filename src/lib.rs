@@ -8,6 +8,7 @@ pub mod gesture;
 pub mod io;
 pub mod marker;
 pub mod model;
+pub mod palette;
 pub mod pixel;
 pub mod png;
 pub mod region_ext;
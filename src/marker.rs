@@ -1,19 +1,26 @@
 use self::{
-    fill::FillMarker, lasso::LassoMarker, noop::NoopMarker, pick::PickMarker, stroke::StrokeMarker,
+    ellipse::EllipseMarker, fill::FillMarker, gradient::GradientMarker, lasso::LassoMarker,
+    line::LineMarker, noop::NoopMarker, pick::PickMarker, rectangle::RectangleMarker,
+    stroke::StrokeMarker,
 };
 use crate::{
     app::App,
     event::{Event, MouseAction},
     io::IoRequest,
-    pixel::{PixelPosition, PixelRegion},
+    model::tool::{SymmetryMode, SymmetryState},
+    pixel::{Pixel, PixelPosition, PixelRegion},
 };
 use pagurus::Result;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+pub mod ellipse;
 pub mod fill;
+pub mod gradient;
 pub mod lasso;
+pub mod line;
 pub mod noop;
 pub mod pick;
+pub mod rectangle;
 pub mod stroke;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
@@ -38,6 +45,12 @@ pub enum MarkerKind {
     Lasso,
     Pick,
     Fill,
+    Line,
+    Rectangle,
+    FilledRectangle,
+    Ellipse,
+    FilledEllipse,
+    Gradient,
 }
 
 #[derive(Debug)]
@@ -47,16 +60,35 @@ pub enum Marker {
     Lasso(LassoMarker),
     Pick(PickMarker),
     Fill(FillMarker),
+    Line(LineMarker),
+    Rectangle(RectangleMarker),
+    Ellipse(EllipseMarker),
+    Gradient(GradientMarker),
 }
 
 impl Marker {
     fn from_kind(kind: MarkerKind) -> Self {
         match kind {
-            MarkerKind::Stroke => Self::Stroke(Default::default()),
+            MarkerKind::Stroke => Self::Stroke(StrokeMarker::with_brush()),
             MarkerKind::Noop => Self::Noop(Default::default()),
             MarkerKind::Lasso => Self::Lasso(Default::default()),
             MarkerKind::Pick => Self::Pick(Default::default()),
             MarkerKind::Fill => Self::Fill(Default::default()),
+            MarkerKind::Line => Self::Line(Default::default()),
+            MarkerKind::Rectangle => Self::Rectangle(Default::default()),
+            MarkerKind::FilledRectangle => Self::Rectangle(RectangleMarker::filled()),
+            MarkerKind::Ellipse => Self::Ellipse(Default::default()),
+            MarkerKind::FilledEllipse => Self::Ellipse(EllipseMarker::filled()),
+            MarkerKind::Gradient => Self::Gradient(Default::default()),
+        }
+    }
+
+    // Pixels having their own colors instead of the drawing color
+    fn colored_pixels<'a>(&'a self, app: &'a App) -> Option<Box<dyn 'a + Iterator<Item = Pixel>>> {
+        match self {
+            Self::Gradient(x) => Some(Box::new(x.gradient_pixels(app))),
+            Self::Stroke(x) => Some(Box::new(x.brush_pixels()?)),
+            _ => None,
         }
     }
 }
@@ -75,6 +107,10 @@ impl Mark for Marker {
             Marker::Lasso(x) => x.mark(app, position, mouse),
             Marker::Pick(x) => x.mark(app, position, mouse),
             Marker::Fill(x) => x.mark(app, position, mouse),
+            Marker::Line(x) => x.mark(app, position, mouse),
+            Marker::Rectangle(x) => x.mark(app, position, mouse),
+            Marker::Ellipse(x) => x.mark(app, position, mouse),
+            Marker::Gradient(x) => x.mark(app, position, mouse),
         }
     }
 
@@ -85,6 +121,10 @@ impl Mark for Marker {
             Marker::Lasso(x) => x.marked_pixels(app),
             Marker::Pick(x) => x.marked_pixels(app),
             Marker::Fill(x) => x.marked_pixels(app),
+            Marker::Line(x) => x.marked_pixels(app),
+            Marker::Rectangle(x) => x.marked_pixels(app),
+            Marker::Ellipse(x) => x.marked_pixels(app),
+            Marker::Gradient(x) => x.marked_pixels(app),
         }
     }
 }
@@ -105,7 +145,55 @@ impl MarkerHandler {
             Marker::Lasso(_) => MarkerKind::Lasso,
             Marker::Pick(_) => MarkerKind::Pick,
             Marker::Fill(_) => MarkerKind::Fill,
+            Marker::Line(_) => MarkerKind::Line,
+            Marker::Rectangle(ref x) if x.is_filled() => MarkerKind::FilledRectangle,
+            Marker::Rectangle(_) => MarkerKind::Rectangle,
+            Marker::Ellipse(ref x) if x.is_filled() => MarkerKind::FilledEllipse,
+            Marker::Ellipse(_) => MarkerKind::Ellipse,
+            Marker::Gradient(_) => MarkerKind::Gradient,
+        }
+    }
+
+    pub fn colored_pixels<'a>(
+        &'a self,
+        app: &'a App,
+    ) -> Option<Box<dyn 'a + Iterator<Item = Pixel>>> {
+        let pixels = self.marker.colored_pixels(app)?;
+        let Some(transform) = self.transform(app) else {
+            return Some(pixels);
+        };
+        let transformed = pixels
+            .flat_map(|pixel| {
+                transform
+                    .apply(pixel.position)
+                    .map(move |position| (position, pixel.color))
+            })
+            .collect::<HashMap<_, _>>();
+        Some(Box::new(
+            transformed
+                .into_iter()
+                .map(|(position, color)| Pixel::new(position, color)),
+        ))
+    }
+
+    // Selecting markers are neither mirrored nor wrapped, and picking markers are not mirrored
+    fn transform(&self, app: &App) -> Option<PixelTransform> {
+        if matches!(self.marker, Marker::Noop(_) | Marker::Lasso(_)) {
+            return None;
         }
+        let mut symmetry = app.models().tool.symmetry;
+        if matches!(self.marker, Marker::Pick(_)) {
+            symmetry.mode = SymmetryMode::None;
+        }
+        let tile = app.models().config.tiling_region(app);
+        if symmetry.mode == SymmetryMode::None && tile.is_none() {
+            return None;
+        }
+        Some(PixelTransform {
+            symmetry,
+            frame: app.models().config.frame.get_base_region(),
+            tile,
+        })
     }
 
     pub fn set_marker_kind(&mut self, kind: MarkerKind) {
@@ -113,7 +201,14 @@ impl MarkerHandler {
     }
 
     pub fn marked_pixels(&self, app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
-        self.marker.marked_pixels(app)
+        let pixels = self.marker.marked_pixels(app);
+        let Some(transform) = self.transform(app) else {
+            return pixels;
+        };
+        let transformed = pixels
+            .flat_map(|position| transform.apply(position))
+            .collect::<HashSet<_>>();
+        Box::new(transformed.into_iter())
     }
 
     pub fn is_completed(&self) -> bool {
@@ -179,9 +274,17 @@ impl MarkerHandler {
             }
         }
 
+        // Point markers on a repeated copy of the tiling frame act on the frame itself
+        let pixel_position = match app.models().config.tiling_region(app) {
+            Some(tile) if matches!(self.marker, Marker::Pick(_) | Marker::Fill(_)) => {
+                tile.wrap(pixel_position)
+            }
+            _ => pixel_position,
+        };
         self.marker.mark(app, pixel_position, self.mouse);
-        let marked = self.marker.marked_pixels(app).collect::<HashSet<_>>();
-        if old_mouse != self.mouse {
+        let marked = self.marked_pixels(app).collect::<HashSet<_>>();
+        // The colors of marked pixels can change even if their positions don't
+        if old_mouse != self.mouse || self.marker.colored_pixels(app).is_some() {
             self.request_redraw(app, marked.union(&self.last_marked).copied());
         } else {
             self.request_redraw(app, marked.symmetric_difference(&self.last_marked).copied());
@@ -196,3 +299,18 @@ impl MarkerHandler {
         app.request_redraw(region);
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+struct PixelTransform {
+    symmetry: SymmetryState,
+    frame: PixelRegion,
+    tile: Option<PixelRegion>,
+}
+
+impl PixelTransform {
+    fn apply(self, position: PixelPosition) -> impl Iterator<Item = PixelPosition> {
+        self.symmetry
+            .mirror(self.frame, position)
+            .map(move |p| self.tile.map_or(p, |tile| tile.wrap(p)))
+    }
+}
@@ -0,0 +1,74 @@
+use super::{Mark, MouseState};
+use crate::{
+    app::App,
+    pixel::{PixelEllipse, PixelPosition, PixelRegion},
+};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+pub struct EllipseMarker {
+    filled: bool,
+    start: Option<PixelPosition>,
+    marked: HashSet<PixelPosition>,
+}
+
+impl EllipseMarker {
+    pub fn filled() -> Self {
+        Self {
+            filled: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled
+    }
+
+    fn ellipse(&self, start: PixelPosition, end: PixelPosition) -> HashSet<PixelPosition> {
+        let ellipse = PixelEllipse::new(PixelRegion::from_positions([start, end].into_iter()));
+        if self.filled {
+            ellipse.pixels().collect()
+        } else {
+            ellipse.edges().collect()
+        }
+    }
+}
+
+impl Mark for EllipseMarker {
+    fn mark(&mut self, app: &App, position: PixelPosition, mouse: MouseState) {
+        let unit = app.models().config.minimum_pixel_size;
+        let position = unit.normalize(position);
+        match mouse {
+            MouseState::Neutral => {
+                self.start = None;
+                self.marked = [position].into_iter().collect();
+            }
+            MouseState::Pressing => {
+                if let Some(start) = self.start {
+                    self.marked = self.ellipse(start, position);
+                } else {
+                    self.start = Some(position);
+                    self.marked = [position].into_iter().collect()
+                }
+            }
+            MouseState::Clicked | MouseState::ClickConsumed => {
+                if let Some(start) = self.start {
+                    self.marked = self.ellipse(start, position);
+                    self.start = None;
+                } else {
+                    self.marked = [position].into_iter().collect()
+                }
+            }
+        }
+    }
+
+    fn marked_pixels(&self, app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
+        let unit = app.models().config.minimum_pixel_size;
+        Box::new(
+            self.marked
+                .iter()
+                .copied()
+                .flat_map(move |p| unit.denormalize_to_region(p).pixels()),
+        )
+    }
+}
@@ -1,5 +1,10 @@
 use super::{Mark, MouseState};
-use crate::{app::App, pixel::PixelPosition};
+use crate::{
+    app::App,
+    model::{Models, tool::FillBound},
+    pixel::{PixelPosition, PixelRegion},
+};
+use pagurus::image::Rgba;
 use std::collections::HashSet;
 
 #[derive(Debug, Default)]
@@ -9,15 +14,40 @@ pub struct FillMarker {
 }
 
 impl FillMarker {
-    fn fill_same_color_area(&mut self, app: &App, position: PixelPosition) {
+    fn fill_same_color_area(&mut self, models: &Models, position: PixelPosition) {
         self.marked.clear();
-        let pixel_canvas = &app.models().pixel_canvas;
+        let options = &models.tool.fill;
+        let pixel_canvas = &models.pixel_canvas;
         let region = pixel_canvas.region();
         let color = pixel_canvas.get_direct_pixel(position);
+        let is_target = |c| color_distance(color, c) <= options.tolerance;
+        let bound = Bound::new(models, position);
+        if !bound.contains(position) {
+            return;
+        }
+
+        // Empty pixels are unbounded, so they are always filled contiguously
+        if options.global && (!is_target(None) || !bound.is_unbounded()) {
+            self.marked.extend(
+                pixel_canvas
+                    .raw_pixels()
+                    .filter(|&(p, c)| bound.contains(p) && is_target(Some(c)))
+                    .map(|(p, _)| p),
+            );
+            if is_target(None) {
+                self.marked.extend(
+                    bound
+                        .pixels()
+                        .filter(|&p| pixel_canvas.get_direct_pixel(p).is_none()),
+                );
+            }
+            return;
+        }
+
         let mut stack = vec![position];
         let mut visited = HashSet::new();
         while let Some(position) = stack.pop() {
-            if color.is_none() && !region.contains(position) {
+            if !region.contains(position) && is_target(None) && bound.is_unbounded() {
                 self.cannot_fill.extend(self.marked.drain());
                 break;
             }
@@ -25,7 +55,7 @@ impl FillMarker {
                 // Already visited.
                 continue;
             }
-            if pixel_canvas.get_direct_pixel(position) != color {
+            if !bound.contains(position) || !is_target(pixel_canvas.get_direct_pixel(position)) {
                 continue;
             }
 
@@ -36,20 +66,239 @@ impl FillMarker {
                 position.move_y(-1),
                 position.move_y(1),
             ]);
+            if options.diagonal {
+                stack.extend([
+                    position.move_x(-1).move_y(-1),
+                    position.move_x(1).move_y(-1),
+                    position.move_x(-1).move_y(1),
+                    position.move_x(1).move_y(1),
+                ]);
+            }
         }
     }
 }
 
+#[derive(Debug)]
+enum Bound<'a> {
+    Unbounded,
+    Region(PixelRegion),
+    Selection(&'a HashSet<PixelPosition>),
+}
+
+impl<'a> Bound<'a> {
+    fn new(models: &'a Models, start: PixelPosition) -> Self {
+        match models.tool.fill.bound {
+            FillBound::None => Self::Unbounded,
+            FillBound::Frame => {
+                // The frame (of the animation frame and layer) that contains the starting pixel
+                let config = &models.config;
+                let base = config.frame.get_base_region();
+                let size = base.size();
+                if size.width == 0
+                    || size.height == 0
+                    || !config.frame.get_full_region(config).contains(start)
+                {
+                    return Self::Unbounded;
+                }
+                let x = (start.x - base.start.x) / size.width as i16;
+                let y = (start.y - base.start.y) / size.height as i16;
+                Self::Region(
+                    base.move_x(x * size.width as i16)
+                        .move_y(y * size.height as i16),
+                )
+            }
+            FillBound::Selection => Self::Selection(&models.selection),
+        }
+    }
+
+    fn is_unbounded(&self) -> bool {
+        matches!(self, Self::Unbounded)
+    }
+
+    fn contains(&self, position: PixelPosition) -> bool {
+        match self {
+            Self::Unbounded => true,
+            Self::Region(region) => region.contains(position),
+            Self::Selection(pixels) => pixels.contains(&position),
+        }
+    }
+
+    fn pixels(&self) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
+        match self {
+            Self::Unbounded => Box::new(std::iter::empty()),
+            Self::Region(region) => Box::new(region.pixels()),
+            Self::Selection(pixels) => Box::new(pixels.iter().copied()),
+        }
+    }
+}
+
+// An empty pixel is regarded as a fully transparent one
+fn color_distance(a: Option<Rgba>, b: Option<Rgba>) -> u8 {
+    match (a, b) {
+        (None, None) => 0,
+        (Some(c), None) | (None, Some(c)) => c.a,
+        (Some(a), Some(b)) => [
+            a.r.abs_diff(b.r),
+            a.g.abs_diff(b.g),
+            a.b.abs_diff(b.b),
+            a.a.abs_diff(b.a),
+        ]
+        .into_iter()
+        .max()
+        .unwrap_or(0),
+    }
+}
+
 impl Mark for FillMarker {
     fn mark(&mut self, app: &App, position: PixelPosition, _mouse: MouseState) {
         if self.marked.contains(&position) || self.cannot_fill.contains(&position) {
             return;
         }
 
-        self.fill_same_color_area(app, position);
+        self.fill_same_color_area(app.models(), position);
     }
 
     fn marked_pixels(&self, _app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
         Box::new(self.marked.iter().copied())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel::Pixel;
+    use orfail::{OrFail, Result};
+
+    fn models(pixels: &[(i16, i16, Rgba)]) -> Result<Models> {
+        let mut models = Models::default();
+        models
+            .pixel_canvas
+            .draw_pixels(
+                &models.config,
+                pixels
+                    .iter()
+                    .map(|&(x, y, c)| Pixel::new(PixelPosition::from_xy(x, y), c)),
+            )
+            .or_fail()?;
+        Ok(models)
+    }
+
+    fn fill(models: &Models, x: i16, y: i16) -> Vec<(i16, i16)> {
+        let mut marker = FillMarker::default();
+        marker.fill_same_color_area(models, PixelPosition::from_xy(x, y));
+        let mut marked = marker.marked.iter().map(|p| (p.x, p.y)).collect::<Vec<_>>();
+        marked.sort();
+        marked
+    }
+
+    fn ring(x: i16, y: i16, color: Rgba) -> Vec<(i16, i16, Rgba)> {
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&d| d != (0, 0))
+            .map(|(dx, dy)| (x + dx, y + dy, color))
+            .collect()
+    }
+
+    #[test]
+    fn color_distance_works() {
+        let a = Rgba::new(10, 20, 30, 255);
+        let b = Rgba::new(15, 10, 30, 250);
+        assert_eq!(color_distance(Some(a), Some(b)), 10);
+        assert_eq!(color_distance(None, None), 0);
+        assert_eq!(color_distance(Some(a), None), 255);
+        assert_eq!(color_distance(None, Some(Rgba::new(10, 20, 30, 0))), 0);
+    }
+
+    #[test]
+    fn fill_tolerance_works() -> Result<()> {
+        let mut models = models(&[
+            (0, 0, Rgba::new(100, 0, 0, 255)),
+            (1, 0, Rgba::new(110, 0, 0, 255)),
+            (2, 0, Rgba::new(130, 0, 0, 255)),
+        ])
+        .or_fail()?;
+        assert_eq!(fill(&models, 0, 0), [(0, 0)]);
+
+        models.tool.fill.tolerance = 10;
+        assert_eq!(fill(&models, 0, 0), [(0, 0), (1, 0)]);
+
+        models.tool.fill.tolerance = 30;
+        assert_eq!(fill(&models, 0, 0), [(0, 0), (1, 0), (2, 0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn fill_treats_empty_pixels_as_transparent() -> Result<()> {
+        let transparent = Rgba::new(10, 20, 30, 5);
+        let mut pixels = ring(1, 1, Rgba::new(0, 0, 0, 255));
+        // The right side of the ring
+        pixels[4].2 = transparent;
+        pixels.push((5, 5, transparent));
+        let mut models = models(&pixels).or_fail()?;
+        assert_eq!(fill(&models, 1, 1), [(1, 1)]);
+        assert_eq!(fill(&models, 5, 5), [(5, 5)]);
+
+        // Both fills spread into the unbounded empty area
+        models.tool.fill.tolerance = 5;
+        assert_eq!(fill(&models, 1, 1), []);
+        assert_eq!(fill(&models, 5, 5), []);
+        Ok(())
+    }
+
+    #[test]
+    fn global_fill_works() -> Result<()> {
+        let red = Rgba::new(255, 0, 0, 255);
+        let mut pixels = ring(1, 1, red);
+        pixels.extend(ring(11, 1, red));
+        let mut models = models(&pixels).or_fail()?;
+        models.tool.fill.global = true;
+
+        let marked = fill(&models, 0, 0);
+        assert_eq!(marked.len(), 16);
+        assert!(marked.contains(&(12, 2)));
+
+        // An empty target is unbounded, so it falls back to the contiguous fill
+        assert_eq!(fill(&models, 1, 1), [(1, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn diagonal_fill_works() -> Result<()> {
+        let red = Rgba::new(255, 0, 0, 255);
+        let mut models = models(&[(0, 0, red), (1, 1, red), (2, 2, red), (2, 0, red)]).or_fail()?;
+        assert_eq!(fill(&models, 0, 0), [(0, 0)]);
+
+        models.tool.fill.diagonal = true;
+        assert_eq!(fill(&models, 0, 0), [(0, 0), (1, 1), (2, 0), (2, 2)]);
+        Ok(())
+    }
+
+    #[test]
+    fn fill_from_empty_pixel_is_clipped_to_bound() -> Result<()> {
+        let mut models = models(&[(5, 1, Rgba::new(255, 0, 0, 255))]).or_fail()?;
+        models.config.frame.set_width(4);
+        models.config.frame.set_height(4);
+        models.config.animation.set_enabled(true);
+        models.config.animation.set_frame_count(2);
+        assert_eq!(fill(&models, 6, 2), []);
+
+        models.tool.fill.bound = FillBound::Frame;
+        let frame = (4..8)
+            .flat_map(|x| (0..4).map(move |y| (x, y)))
+            .filter(|&p| p != (5, 1))
+            .collect::<Vec<_>>();
+        assert_eq!(fill(&models, 6, 2), frame);
+        models.tool.fill.global = true;
+        assert_eq!(fill(&models, 6, 2), frame);
+        models.tool.fill.global = false;
+
+        models.tool.fill.bound = FillBound::Selection;
+        models.selection = [(6, 2), (7, 2), (6, 3), (20, 20)]
+            .into_iter()
+            .map(|(x, y)| PixelPosition::from_xy(x, y))
+            .collect();
+        assert_eq!(fill(&models, 6, 2), [(6, 2), (6, 3), (7, 2)]);
+        assert_eq!(fill(&models, 5, 2), []);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use super::{Mark, MouseState, fill::FillMarker};
+use crate::{
+    app::App,
+    model::tool::DitherPattern,
+    pixel::{Pixel, PixelPosition},
+};
+use pagurus::image::Rgba;
+
+#[derive(Debug, Default)]
+pub struct GradientMarker {
+    area: FillMarker,
+    start: Option<PixelPosition>,
+    end: PixelPosition,
+}
+
+impl GradientMarker {
+    pub fn gradient_pixels<'a>(&'a self, app: &'a App) -> impl 'a + Iterator<Item = Pixel> {
+        let config = &app.models().config;
+        let gradient = &app.models().tool.gradient;
+        let colors = [
+            config.drawing_color(),
+            config.palette.restrict(gradient.color),
+        ];
+        let start = self.start.unwrap_or(self.end);
+        let dx = f32::from(self.end.x - start.x);
+        let dy = f32::from(self.end.y - start.y);
+        let length = dx * dx + dy * dy;
+        self.area.marked_pixels(app).map(move |position| {
+            let t = if length == 0.0 {
+                0.0
+            } else {
+                let x = f32::from(position.x - start.x);
+                let y = f32::from(position.y - start.y);
+                ((x * dx + y * dy) / length).clamp(0.0, 1.0)
+            };
+            let (level, levels) = threshold(gradient.pattern, &gradient.custom, position);
+            let color = colors[usize::from(t * levels as f32 > level as f32 + 0.5)];
+            Pixel::new(position, color)
+        })
+    }
+}
+
+// Returns the threshold level of `position` and the number of levels of `pattern`
+fn threshold(pattern: DitherPattern, custom: &[Vec<u8>], position: PixelPosition) -> (u32, u32) {
+    let x = position.x.rem_euclid(8) as u32;
+    let y = position.y.rem_euclid(8) as u32;
+    match pattern {
+        DitherPattern::Bayer2 => (bayer(1, x, y), 4),
+        DitherPattern::Bayer4 => (bayer(2, x, y), 16),
+        DitherPattern::Bayer8 => (bayer(3, x, y), 64),
+        DitherPattern::Checker => ((x + y) % 2, 2),
+        DitherPattern::Custom => {
+            let Some(width) = custom.first().map(|row| row.len()).filter(|&n| n > 0) else {
+                return (0, 1);
+            };
+            let row = &custom[position.y.rem_euclid(custom.len() as i16) as usize];
+            let level = row[position.x.rem_euclid(width as i16) as usize];
+            (u32::from(level), 256)
+        }
+    }
+}
+
+// The value of the `2^order` x `2^order` Bayer matrix at (x, y)
+fn bayer(order: u32, x: u32, y: u32) -> u32 {
+    (0..order).fold(0, |value, i| {
+        let (xb, yb) = ((x >> i) & 1, (y >> i) & 1);
+        (value << 2) | ((xb ^ yb) << 1) | yb
+    })
+}
+
+pub fn gray_level(color: Rgba) -> u8 {
+    let luminance =
+        (u32::from(color.r) * 299 + u32::from(color.g) * 587 + u32::from(color.b) * 114) / 1000;
+    (luminance * u32::from(color.a) / 255) as u8
+}
+
+impl Mark for GradientMarker {
+    fn mark(&mut self, app: &App, position: PixelPosition, mouse: MouseState) {
+        match mouse {
+            MouseState::Pressing | MouseState::Clicked if self.start.is_some() => {
+                self.end = position;
+            }
+            MouseState::Pressing => {
+                self.area.mark(app, position, mouse);
+                self.start = Some(position);
+                self.end = position;
+            }
+            _ => {
+                self.area.mark(app, position, mouse);
+                self.start = None;
+                self.end = position;
+            }
+        }
+    }
+
+    fn marked_pixels(&self, app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
+        self.area.marked_pixels(app)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(order: u32) -> Vec<Vec<u32>> {
+        let n = 1 << order;
+        (0..n)
+            .map(|y| (0..n).map(|x| bayer(order, x, y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn bayer_works() {
+        assert_eq!(matrix(1), [[0, 2], [3, 1]]);
+        assert_eq!(
+            matrix(2),
+            [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]]
+        );
+
+        let mut levels = matrix(3).concat();
+        levels.sort();
+        assert_eq!(levels, (0..64).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn custom_threshold_works() {
+        let custom = [vec![0, 128, 32], vec![255, 64, 16]];
+        let level = |x, y| threshold(DitherPattern::Custom, &custom, PixelPosition::from_xy(x, y));
+        assert_eq!(level(0, 0), (0, 256));
+        assert_eq!(level(1, 0), (128, 256));
+        assert_eq!(level(2, 1), (16, 256));
+
+        // The pattern is tiled in both directions
+        assert_eq!(level(4, 2), (128, 256));
+        assert_eq!(level(-1, -1), (16, 256));
+        assert_eq!(level(-3, 3), (255, 256));
+
+        assert_eq!(
+            threshold(DitherPattern::Custom, &[], PixelPosition::from_xy(1, 1)),
+            (0, 1)
+        );
+        assert_eq!(
+            threshold(
+                DitherPattern::Custom,
+                &[vec![]],
+                PixelPosition::from_xy(1, 1)
+            ),
+            (0, 1)
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use super::{Mark, MouseState};
+use crate::{
+    app::App,
+    pixel::{PixelPosition, PixelRegion},
+};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+pub struct RectangleMarker {
+    filled: bool,
+    start: Option<PixelPosition>,
+    marked: HashSet<PixelPosition>,
+}
+
+impl RectangleMarker {
+    pub fn filled() -> Self {
+        Self {
+            filled: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.filled
+    }
+
+    fn rectangle(&self, start: PixelPosition, end: PixelPosition) -> HashSet<PixelPosition> {
+        let region = PixelRegion::from_positions([start, end].into_iter());
+        if self.filled {
+            region.pixels().collect()
+        } else {
+            region.edges().collect()
+        }
+    }
+}
+
+impl Mark for RectangleMarker {
+    fn mark(&mut self, app: &App, position: PixelPosition, mouse: MouseState) {
+        let unit = app.models().config.minimum_pixel_size;
+        let position = unit.normalize(position);
+        match mouse {
+            MouseState::Neutral => {
+                self.start = None;
+                self.marked = [position].into_iter().collect();
+            }
+            MouseState::Pressing => {
+                if let Some(start) = self.start {
+                    self.marked = self.rectangle(start, position);
+                } else {
+                    self.start = Some(position);
+                    self.marked = [position].into_iter().collect()
+                }
+            }
+            MouseState::Clicked | MouseState::ClickConsumed => {
+                if let Some(start) = self.start {
+                    self.marked = self.rectangle(start, position);
+                    self.start = None;
+                } else {
+                    self.marked = [position].into_iter().collect()
+                }
+            }
+        }
+    }
+
+    fn marked_pixels(&self, app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
+        let unit = app.models().config.minimum_pixel_size;
+        Box::new(
+            self.marked
+                .iter()
+                .copied()
+                .flat_map(move |p| unit.denormalize_to_region(p).pixels()),
+        )
+    }
+}
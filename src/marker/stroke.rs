@@ -1,23 +1,128 @@
 use super::{Mark, MouseState};
 use crate::{
     app::App,
-    pixel::{PixelLine, PixelPosition},
+    model::tool::BrushKind,
+    pixel::{Pixel, PixelLine, PixelPosition},
 };
-use std::collections::HashSet;
+use pagurus::image::Rgba;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Default)]
 pub struct StrokeMarker {
+    use_brush: bool,
+    brush: BrushKind,
     last: Option<PixelPosition>,
+    path: Vec<PixelPosition>,
+    path_counts: HashMap<PixelPosition, usize>,
     marked: HashSet<PixelPosition>,
+    colors: HashMap<PixelPosition, Rgba>,
+}
+
+impl StrokeMarker {
+    pub fn with_brush() -> Self {
+        Self {
+            use_brush: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn brush_pixels(&self) -> Option<impl '_ + Iterator<Item = Pixel>> {
+        (self.brush == BrushKind::Custom).then(|| {
+            self.colors
+                .iter()
+                .map(|(&position, &color)| Pixel::new(position, color))
+        })
+    }
+
+    fn stamp(&mut self, brush: &[Pixel], position: PixelPosition) {
+        for pixel in brush {
+            let p = position + pixel.position;
+            self.marked.insert(p);
+            self.colors.insert(p, pixel.color);
+        }
+    }
+
+    // Brushes other than `BrushKind::Square` are not aligned to the minimum pixel size grid
+    fn mark_with_brush(&mut self, app: &App, position: PixelPosition) {
+        let state = &app.models().tool.brush;
+        let brush = match self.brush {
+            BrushKind::Square => return,
+            BrushKind::Round => {
+                let color = app.models().config.drawing_color();
+                round_brush(state.diameter)
+                    .map(|p| Pixel::new(p, color))
+                    .collect()
+            }
+            BrushKind::Custom => state.custom.clone(),
+        };
+        if let Some(last) = self.last {
+            for p in PixelLine::new(last, position).pixels() {
+                self.stamp(&brush, p);
+            }
+        } else {
+            self.marked.clear();
+            self.colors.clear();
+            self.stamp(&brush, position);
+        }
+    }
+
+    fn push(&mut self, position: PixelPosition, pixel_perfect: bool) {
+        if self.path.last() == Some(&position) {
+            return;
+        }
+        self.path.push(position);
+        *self.path_counts.entry(position).or_default() += 1;
+        self.marked.insert(position);
+
+        let n = self.path.len();
+        if !pixel_perfect || n < 3 {
+            return;
+        }
+
+        // Drop the corner pixel of an L-shaped step.
+        let (a, b, c) = (self.path[n - 3], self.path[n - 2], self.path[n - 1]);
+        if (a.x == b.x || a.y == b.y)
+            && (b.x == c.x || b.y == c.y)
+            && (a.x - c.x).abs() == 1
+            && (a.y - c.y).abs() == 1
+        {
+            self.path.remove(n - 2);
+            if let Some(count) = self.path_counts.get_mut(&b) {
+                *count -= 1;
+                if *count == 0 {
+                    self.path_counts.remove(&b);
+                    self.marked.remove(&b);
+                }
+            }
+        }
+    }
 }
 
 impl Mark for StrokeMarker {
     fn mark(&mut self, app: &App, position: PixelPosition, mouse: MouseState) {
+        if self.last.is_none() && self.use_brush {
+            self.brush = app.models().tool.brush.kind;
+        }
+        if self.brush != BrushKind::Square {
+            self.mark_with_brush(app, position);
+            self.last = (mouse == MouseState::Pressing).then_some(position);
+            return;
+        }
+
         let unit = app.models().config.minimum_pixel_size;
+        let pixel_perfect = app.models().config.pixel_perfect;
         let position = unit.normalize(position);
         if let Some(last) = self.last {
-            self.marked.extend(PixelLine::new(last, position).pixels());
+            let mut line = PixelLine::new(last, position).pixels().collect::<Vec<_>>();
+            if line.first() != Some(&last) {
+                line.reverse();
+            }
+            for p in line {
+                self.push(p, pixel_perfect);
+            }
         } else {
+            self.path = vec![position];
+            self.path_counts = [(position, 1)].into_iter().collect();
             self.marked = [position].into_iter().collect()
         }
         if mouse == MouseState::Pressing {
@@ -28,6 +133,10 @@ impl Mark for StrokeMarker {
     }
 
     fn marked_pixels(&self, app: &App) -> Box<dyn '_ + Iterator<Item = PixelPosition>> {
+        if self.brush != BrushKind::Square {
+            return Box::new(self.marked.iter().copied());
+        }
+
         let unit = app.models().config.minimum_pixel_size;
         Box::new(
             self.marked
@@ -37,3 +146,66 @@ impl Mark for StrokeMarker {
         )
     }
 }
+
+// Offsets of the pixels of a round brush from its center
+fn round_brush(diameter: u8) -> impl Iterator<Item = PixelPosition> {
+    let d = i16::from(diameter.max(1));
+    let r = f32::from(d) / 2.0;
+    let limit = (r - 0.25).powi(2);
+    (0..d)
+        .flat_map(move |y| (0..d).map(move |x| PixelPosition::from_xy(x, y)))
+        .filter(move |p| {
+            let dx = f32::from(p.x) + 0.5 - r;
+            let dy = f32::from(p.y) + 0.5 - r;
+            dx * dx + dy * dy <= limit
+        })
+        .map(move |p| PixelPosition::from_xy(p.x - d / 2, p.y - d / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(xys: &[(i16, i16)]) -> Vec<PixelPosition> {
+        xys.iter()
+            .map(|&(x, y)| PixelPosition::from_xy(x, y))
+            .collect()
+    }
+
+    fn marked(marker: &StrokeMarker) -> Vec<PixelPosition> {
+        let mut marked = marker.marked.iter().copied().collect::<Vec<_>>();
+        marked.sort();
+        marked
+    }
+
+    #[test]
+    fn pixel_perfect_push_works() {
+        let mut marker = StrokeMarker::default();
+        for p in positions(&[(0, 0), (1, 0), (1, 1), (2, 1), (3, 1)]) {
+            marker.push(p, true);
+        }
+        let path = positions(&[(0, 0), (1, 1), (2, 1), (3, 1)]);
+        assert_eq!(marker.path, path);
+        assert_eq!(marked(&marker), path);
+    }
+
+    #[test]
+    fn pixel_perfect_push_keeps_revisited_pixels() {
+        let mut marker = StrokeMarker::default();
+        for p in positions(&[(1, 0), (1, 1), (0, 1), (0, 0), (1, 0)]) {
+            marker.push(p, false);
+        }
+
+        // The dropped corner (1, 0) is still a part of the path
+        marker.push(PixelPosition::from_xy(1, -1), true);
+        assert_eq!(
+            marker.path,
+            positions(&[(1, 0), (1, 1), (0, 1), (0, 0), (1, -1)])
+        );
+        assert_eq!(
+            marked(&marker),
+            positions(&[(1, -1), (0, 0), (1, 0), (0, 1), (1, 1)])
+        );
+        assert_eq!(marker.path_counts[&PixelPosition::from_xy(1, 0)], 1);
+    }
+}
@@ -1,6 +1,10 @@
-use self::{config::ConfigModel, pixel_canvas::PixelCanvasModel, tool::ToolModel};
+use self::{
+    config::{Animation, ConfigModel, FrameDurations, Layer},
+    pixel_canvas::PixelCanvasModel,
+    tool::ToolModel,
+};
 use crate::pixel::PixelSize;
-use crate::png::decode_sprite;
+use crate::png::{AnimationFrame, decode_animation_frames, encode_rgba, to_indexed};
 use crate::{
     pixel::{Pixel, PixelPosition},
     serialize::{Deserialize, Serialize},
@@ -20,6 +24,45 @@ pub const PNG_CHUNK_TYPE: ChunkType = ChunkType(*b"siLE");
 pub const MAGIC_NUMBER: [u8; 6] = *b"PIXCIL";
 pub const FORMAT_VERSION: u16 = 0;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpriteSheetOptions {
+    // Cell size, or the number of columns and rows if `grid` is `true`.
+    pub size: PixelSize,
+    pub grid: bool,
+    pub rows_as_layers: bool,
+}
+
+impl SpriteSheetOptions {
+    pub fn cell_size(self, image_size: PixelSize) -> Result<PixelSize> {
+        let cell_size = if self.grid {
+            (self.size.width > 0 && self.size.height > 0).or_fail()?;
+            PixelSize::from_wh(
+                image_size.width / self.size.width,
+                image_size.height / self.size.height,
+            )
+        } else {
+            self.size
+        };
+        (cell_size.width > 0 && cell_size.height > 0)
+            .or_fail_with(|_| "sprite sheet cell size must be non-zero".to_owned())?;
+        (cell_size.width <= image_size.width && cell_size.height <= image_size.height)
+            .or_fail_with(|_| {
+                format!("cell size {cell_size:?} is larger than the image size {image_size:?}")
+            })?;
+        Ok(cell_size)
+    }
+}
+
+impl Default for SpriteSheetOptions {
+    fn default() -> Self {
+        Self {
+            size: PixelSize::square(16),
+            grid: false,
+            rows_as_layers: false,
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Models {
     pub config: ConfigModel,
@@ -28,6 +71,9 @@ pub struct Models {
     // The following fields are not serialized / deserialized.
     pub tool: ToolModel,
     pub preview_mode: bool,
+
+    // The area of the last selection (used to clip the fill tool)
+    pub selection: HashSet<PixelPosition>,
 }
 
 impl Models {
@@ -57,46 +103,89 @@ impl Models {
         palette
     }
 
-    pub fn to_png(&self) -> Result<Vec<u8>> {
+    pub fn frame_image_data(&self, frame: u16) -> Vec<u8> {
         let bg_color = self
             .config
             .background_color
             .unwrap_or(Rgba::new(0, 0, 0, 0));
+        self.config
+            .frame
+            .get_preview_region(&self.config, frame as usize)
+            .pixels()
+            .flat_map(|position| {
+                let color = if let Some(c) = self.pixel_canvas.get_pixel(&self.config, position) {
+                    c.alpha_blend(bg_color)
+                } else {
+                    bg_color
+                };
+                [color.r, color.g, color.b, color.a].into_iter()
+            })
+            .collect()
+    }
+
+    pub fn layer_image_data(&self, layer: u16, frame: u16) -> Vec<u8> {
+        self.config
+            .frame
+            .get_base_region()
+            .shift_y(layer as i16)
+            .shift_x(frame as i16)
+            .pixels()
+            .flat_map(|position| {
+                let color = self
+                    .pixel_canvas
+                    .get_direct_pixel(position)
+                    .unwrap_or(Rgba::new(0, 0, 0, 0));
+                [color.r, color.g, color.b, color.a].into_iter()
+            })
+            .collect()
+    }
+
+    pub fn frame_to_png(&self, frame: u16) -> Result<Vec<u8>> {
+        (frame < self.config.animation.enabled_frame_count()).or_fail()?;
+        let size = self.frame_size();
+        encode_rgba(size, &self.frame_image_data(frame)).or_fail()
+    }
+
+    pub fn layer_to_png(&self, layer: u16) -> Result<Vec<u8>> {
+        (layer < self.config.layer.enabled_count()).or_fail()?;
+        let frames = (0..self.config.animation.enabled_frame_count())
+            .map(|frame| self.layer_image_data(layer, frame))
+            .collect::<Vec<_>>();
+        let size = self.frame_size();
+        let sheet = to_sprite_sheet(size, &frames);
+        let sheet_size = PixelSize::from_wh(size.width * frames.len() as u16, size.height);
+        encode_rgba(sheet_size, &sheet).or_fail()
+    }
+
+    fn frame_delay(&self, frame: usize) -> (u16, u16) {
+        if let Some(ms) = self.config.frame_durations.get_millis(frame) {
+            (ms, 1000)
+        } else {
+            (1, u16::from(self.config.animation.fps()))
+        }
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>> {
         let mut frame_count = self.config.animation.enabled_frame_count();
         let mut frames = (0..frame_count)
-            .map(|frame| {
-                self.config
-                    .frame
-                    .get_preview_region(&self.config, frame as usize)
-                    .pixels()
-                    .flat_map(|position| {
-                        let color =
-                            if let Some(c) = self.pixel_canvas.get_pixel(&self.config, position) {
-                                c.alpha_blend(bg_color)
-                            } else {
-                                bg_color
-                            };
-                        [color.r, color.g, color.b, color.a].into_iter()
-                    })
-                    .collect::<Vec<_>>()
-            })
+            .map(|frame| self.frame_image_data(frame))
             .collect::<Vec<_>>();
         let mut image_size = self.config.frame.get_base_region().size();
         if !self.config.apng() {
-            let mut sprite_sheet_frame = Vec::new();
-            for row in 0..image_size.height {
-                for frame in &frames {
-                    let row_start = row as usize * image_size.width as usize * 4;
-                    let row_end = row_start + image_size.width as usize * 4;
-                    sprite_sheet_frame.extend_from_slice(&frame[row_start..row_end]);
-                }
-            }
-
-            frames = vec![sprite_sheet_frame];
+            frames = vec![to_sprite_sheet(image_size, &frames)];
             image_size.width *= frame_count;
             frame_count = 1;
         }
 
+        let mut indexed = if self.config.indexed_png {
+            to_indexed(usize::from(image_size.width), &frames)
+        } else {
+            None
+        };
+        if let Some(indexed) = &mut indexed {
+            frames = std::mem::take(&mut indexed.frames);
+        }
+
         let mut metadata = Vec::new();
         self.serialize(&mut metadata).or_fail()?;
 
@@ -107,19 +196,29 @@ impl Models {
                 u32::from(image_size.width),
                 u32::from(image_size.height),
             );
-            encoder.set_color(png::ColorType::Rgba);
-            encoder.set_depth(png::BitDepth::Eight);
+            if let Some(indexed) = &indexed {
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(indexed.depth);
+                encoder.set_palette(indexed.palette.clone());
+                if !indexed.trns.is_empty() {
+                    encoder.set_trns(indexed.trns.clone());
+                }
+            } else {
+                encoder.set_color(png::ColorType::Rgba);
+                encoder.set_depth(png::BitDepth::Eight);
+            }
             encoder.set_compression(png::Compression::Best);
 
             if frame_count > 1 {
                 encoder.set_animated(frame_count as u32, 0).or_fail()?;
-                encoder
-                    .set_frame_delay(1, self.config.animation.fps() as u16)
-                    .or_fail()?;
             }
 
             let mut writer = encoder.write_header().or_fail()?;
-            for image_data in &frames {
+            for (frame, image_data) in frames.iter().enumerate() {
+                if frame_count > 1 {
+                    let (numerator, denominator) = self.frame_delay(frame);
+                    writer.set_frame_delay(numerator, denominator).or_fail()?;
+                }
                 writer.write_image_data(image_data).or_fail()?;
             }
             writer.write_chunk(PNG_CHUNK_TYPE, &metadata).or_fail()?;
@@ -144,25 +243,118 @@ impl Models {
         }
 
         // Load the image with the default settings.
-        //
-        // TODO: Support animated PNG
         let mut models = Self::default();
-        let image = decode_sprite(png_data).or_fail()?;
+        let frames = decode_animation_frames(png_data).or_fail()?;
+        let size = frames.first().or_fail()?.image.size();
+        (size.width as usize * frames.len() <= i16::MAX as usize)
+            .or_fail_with(|_| "image is too wide".to_owned())?;
+        (size.height as usize <= i16::MAX as usize)
+            .or_fail_with(|_| "image is too tall".to_owned())?;
+        let pixels = frames.iter().enumerate().flat_map(|(i, frame)| {
+            let x_offset = (size.width as usize * i) as i16;
+            frame
+                .image
+                .pixels()
+                .filter(|(_, rgba)| rgba.a > 0)
+                .map(move |(pos, rgba)| {
+                    Pixel::new(
+                        PixelPosition::from_xy(pos.x as i16 + x_offset, pos.y as i16),
+                        rgba,
+                    )
+                })
+        });
         models
             .pixel_canvas
-            .draw_pixels(
-                &models.config,
-                image.pixels().map(|(pos, rgba)| {
-                    Pixel::new(PixelPosition::from_xy(pos.x as i16, pos.y as i16), rgba)
-                }),
-            )
+            .draw_pixels(&models.config, pixels)
             .or_fail()?;
         models.pixel_canvas.forget_oldest_command();
-        models.config.frame.set_width(image.size().width as u16);
-        models.config.frame.set_height(image.size().height as u16);
+        models.config.frame.set_width(size.width as u16);
+        models.config.frame.set_height(size.height as u16);
+        if frames.len() > 1 {
+            models.config.animation.set_enabled(true);
+            models.config.animation.set_frame_count(frames.len() as u16);
+            models.set_frame_timing(&frames);
+        }
 
         Ok(models)
     }
+
+    pub fn from_sprite_sheet(png_data: &[u8], options: SpriteSheetOptions) -> Result<Self> {
+        let frames = decode_animation_frames(png_data).or_fail()?;
+        let image = &frames.first().or_fail()?.image;
+        let image_size = PixelSize::from_wh(image.size().width as u16, image.size().height as u16);
+        let cell = options.cell_size(image_size).or_fail()?;
+        let columns = image_size.width / cell.width;
+        let rows = image_size.height / cell.height;
+        let (layers, frame_count) = if options.rows_as_layers {
+            (u32::from(rows), u32::from(columns))
+        } else {
+            (1, u32::from(columns) * u32::from(rows))
+        };
+        (layers <= u32::from(Layer::MAX))
+            .or_fail_with(|_| format!("too many layers: {layers} (max {})", Layer::MAX))?;
+        (frame_count <= u32::from(Animation::MAX_FRAME_COUNT)).or_fail_with(|_| {
+            format!(
+                "too many frames: {frame_count} (max {})",
+                Animation::MAX_FRAME_COUNT
+            )
+        })?;
+        (frame_count * u32::from(cell.width) <= i16::MAX as u32)
+            .or_fail_with(|_| "sprite sheet is too wide".to_owned())?;
+        let (layers, frame_count) = (layers as u16, frame_count as u16);
+
+        let mut models = Self::default();
+        let pixels = image.pixels().filter_map(|(pos, rgba)| {
+            let x = pos.x as u16;
+            let y = pos.y as u16;
+            let (column, row) = (x / cell.width, y / cell.height);
+            if rgba.a == 0 || column >= columns || row >= rows {
+                return None;
+            }
+            let (x, y) = if options.rows_as_layers {
+                (x, y)
+            } else {
+                let frame = row * columns + column;
+                (frame * cell.width + x % cell.width, y % cell.height)
+            };
+            Some(Pixel::new(PixelPosition::from_xy(x as i16, y as i16), rgba))
+        });
+        models
+            .pixel_canvas
+            .draw_pixels(&models.config, pixels)
+            .or_fail()?;
+        models.pixel_canvas.forget_oldest_command();
+        models.config.frame.set_from_pixel_size(cell);
+        if layers > 1 {
+            models.config.layer.set_enabled(true);
+            models.config.layer.set_count(layers);
+        }
+        if frame_count > 1 {
+            models.config.animation.set_enabled(true);
+            models.config.animation.set_frame_count(frame_count);
+        }
+        Ok(models)
+    }
+
+    fn set_frame_timing(&mut self, frames: &[AnimationFrame]) {
+        let first = &frames[0];
+        let same_delay = frames
+            .iter()
+            .all(|f| (f.delay_num, f.delay_den) == (first.delay_num, first.delay_den));
+        if same_delay && first.delay_num > 0 && first.delay_den.is_multiple_of(first.delay_num) {
+            let fps = first.delay_den / first.delay_num;
+            if (u16::from(Animation::MIN_FPS)..=u16::from(Animation::MAX_FPS)).contains(&fps) {
+                self.config.animation.set_fps(fps as u8);
+                return;
+            }
+        }
+
+        for (i, frame) in frames.iter().enumerate() {
+            let ms = u32::from(frame.delay_num) * 1000 / u32::from(frame.delay_den);
+            let ms = ms.clamp(1, u32::from(FrameDurations::MAX_MILLIS)) as u16;
+            self.config.frame_durations.set_millis(i, ms);
+        }
+    }
 }
 
 impl Serialize for Models {
@@ -174,7 +366,9 @@ impl Serialize for Models {
         config_size.serialize(writer).or_fail()?;
         self.config.serialize(writer).or_fail()?;
 
-        self.pixel_canvas.serialize(writer).or_fail()?;
+        self.pixel_canvas
+            .serialize_with_command_log(writer, self.config.saved_undos.get() as usize)
+            .or_fail()?;
         Ok(())
     }
 }
@@ -193,7 +387,7 @@ impl Deserialize for Models {
             let mut reader = reader.take(u64::from(config_size));
             config = ConfigModel::deserialize(&mut reader).or_fail()?;
             // Ignore unknown fields.
-            for _ in reader.bytes() {}
+            std::io::copy(&mut reader, &mut std::io::sink()).or_fail()?;
         };
 
         Ok(Self {
@@ -203,3 +397,147 @@ impl Deserialize for Models {
         })
     }
 }
+
+fn to_sprite_sheet(frame_size: PixelSize, frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut sprite_sheet = Vec::new();
+    for row in 0..frame_size.height {
+        for frame in frames {
+            let row_start = row as usize * frame_size.width as usize * 4;
+            let row_end = row_start + frame_size.width as usize * 4;
+            sprite_sheet.extend_from_slice(&frame[row_start..row_end]);
+        }
+    }
+    sprite_sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_png_rejects_too_wide_animation() -> Result<()> {
+        let width = 20000;
+        let mut png_data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png_data, width, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_animated(2, 0).or_fail()?;
+        let mut writer = encoder.write_header().or_fail()?;
+        for _ in 0..2 {
+            writer
+                .write_image_data(&vec![255; width as usize * 4])
+                .or_fail()?;
+        }
+        writer.finish().or_fail()?;
+
+        let error = Models::from_png(&png_data).expect_err("too wide");
+        assert!(error.message.contains("too wide"));
+        Ok(())
+    }
+
+    // A `width` x `height` image whose pixel at (x, y) has the red value `y * width + x + 1`
+    fn sheet(width: u16, height: u16) -> Result<Vec<u8>> {
+        let bytes = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [(y * width + x + 1) as u8, 0, 0, 255]))
+            .collect::<Vec<_>>();
+        encode_rgba(PixelSize::from_wh(width, height), &bytes).or_fail()
+    }
+
+    fn options(width: u16, height: u16, grid: bool, rows_as_layers: bool) -> SpriteSheetOptions {
+        SpriteSheetOptions {
+            size: PixelSize::from_wh(width, height),
+            grid,
+            rows_as_layers,
+        }
+    }
+
+    fn red(models: &Models, x: i16, y: i16) -> Option<u8> {
+        models
+            .pixel_canvas
+            .get_direct_pixel(PixelPosition::from_xy(x, y))
+            .map(|c| c.r)
+    }
+
+    #[test]
+    fn sprite_sheet_cell_size_works() -> Result<()> {
+        let image = PixelSize::from_wh(4, 2);
+        let cell = PixelSize::from_wh(2, 1);
+        assert_eq!(
+            options(2, 1, false, false).cell_size(image).or_fail()?,
+            cell
+        );
+        assert_eq!(options(2, 2, true, false).cell_size(image).or_fail()?, cell);
+        assert_eq!(
+            options(3, 1, true, false).cell_size(image).or_fail()?,
+            PixelSize::from_wh(1, 2)
+        );
+
+        // Cells larger than the image or empty cells are rejected
+        assert!(options(5, 1, false, false).cell_size(image).is_err());
+        assert!(options(2, 3, false, false).cell_size(image).is_err());
+        assert!(options(0, 1, false, false).cell_size(image).is_err());
+        assert!(options(8, 1, true, false).cell_size(image).is_err());
+        assert!(options(0, 1, true, false).cell_size(image).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn from_sprite_sheet_works() -> Result<()> {
+        let png_data = sheet(4, 2).or_fail()?;
+
+        // Cells are ordered row by row as frames
+        for options in [options(2, 1, false, false), options(2, 2, true, false)] {
+            let models = Models::from_sprite_sheet(&png_data, options).or_fail()?;
+            assert_eq!(
+                models.config.frame.get_base_region().size(),
+                PixelSize::from_wh(2, 1)
+            );
+            assert_eq!(models.config.animation.enabled_frame_count(), 4);
+            assert!(!models.config.layer.is_enabled());
+            let frames = (0..4)
+                .map(|frame| [red(&models, frame * 2, 0), red(&models, frame * 2 + 1, 0)])
+                .collect::<Vec<_>>();
+            assert_eq!(
+                frames,
+                [
+                    [Some(1), Some(2)],
+                    [Some(3), Some(4)],
+                    [Some(5), Some(6)],
+                    [Some(7), Some(8)],
+                ]
+            );
+            assert_eq!(red(&models, 0, 1), None);
+        }
+
+        // Rows become layers and the pixels keep their positions
+        let models = Models::from_sprite_sheet(&png_data, options(2, 1, false, true)).or_fail()?;
+        assert_eq!(models.config.animation.enabled_frame_count(), 2);
+        assert!(models.config.layer.is_enabled());
+        assert_eq!(models.config.layer.count(), 2);
+        for (x, y) in [(0, 0), (3, 0), (1, 1), (3, 1)] {
+            assert_eq!(red(&models, x, y), Some((y * 4 + x + 1) as u8));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_sprite_sheet_limits() -> Result<()> {
+        let png_data = sheet(1, Layer::MAX + 1).or_fail()?;
+        let error = Models::from_sprite_sheet(&png_data, options(1, 1, false, true))
+            .expect_err("too many layers");
+        assert!(error.message.contains("too many layers"));
+        let models = Models::from_sprite_sheet(&png_data, options(1, 1, false, false)).or_fail()?;
+        assert_eq!(
+            models.config.animation.enabled_frame_count(),
+            Layer::MAX + 1
+        );
+
+        let png_data = sheet(Animation::MAX_FRAME_COUNT + 1, 1).or_fail()?;
+        let error = Models::from_sprite_sheet(&png_data, options(1, 1, false, false))
+            .expect_err("too many frames");
+        assert!(error.message.contains("too many frames"));
+
+        let png_data = sheet(4, 2).or_fail()?;
+        assert!(Models::from_sprite_sheet(&png_data, options(4, 3, false, false)).is_err());
+        Ok(())
+    }
+}
@@ -6,7 +6,8 @@ use std::{
     time::Duration,
 };
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AttributesModel {
     pub created_time: Option<Duration>,
     pub updated_time: Option<Duration>,
@@ -43,7 +44,7 @@ impl Deserialize for AttributesModel {
             updated_time: Deserialize::deserialize(&mut reader).or_fail()?,
         };
         // Ignore unknown fields.
-        for _ in reader.bytes() {}
+        std::io::copy(&mut reader, &mut std::io::sink()).or_fail()?;
         Ok(this)
     }
 }
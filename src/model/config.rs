@@ -4,7 +4,7 @@ use crate::{
     pixel::{PixelPosition, PixelRegion, PixelSize},
     serialize::{Deserialize, Serialize},
 };
-use orfail::{OrFail, Result};
+use orfail::{Failure, OrFail, Result};
 use pagurus::image::Rgba;
 use pagurus::spatial::Position;
 use std::{
@@ -12,12 +12,12 @@ use std::{
     time::Duration,
 };
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ConfigModel {
     pub zoom: Zoom,
     pub camera: Camera,
     pub minimum_pixel_size: MinimumPixelSize,
-    // TODO: remove this field in the future version
     pub max_undos: MaxUndos,
     pub color: DrawingColor,
     pub frame: FrameRegion,
@@ -34,12 +34,64 @@ pub struct ConfigModel {
     pub gesture: bool,
     pub background_color: Option<Rgba>,
     pub apng: Option<bool>,
+    pub layer_settings: LayerSettings,
+    pub frame_durations: FrameDurations,
+    pub palette: Palette,
+    pub indexed_png: bool,
+    pub pixel_perfect: bool,
+    pub saved_undos: SavedUndos,
+    pub tiling: bool,
+    pub onion_skin: OnionSkin,
 }
 
 impl ConfigModel {
     pub fn apng(&self) -> bool {
         self.apng.unwrap_or(true)
     }
+
+    pub fn layer_index(&self, position: PixelPosition) -> Option<usize> {
+        if !self.layer.is_enabled() {
+            return None;
+        }
+
+        let region = self.frame.get_full_region(self);
+        if !region.contains(position) {
+            return None;
+        }
+
+        let height = self.frame.get_base_region().size().height as i16;
+        Some(((position.y - region.start.y) / height) as usize)
+    }
+
+    pub fn layer_setting(&self, position: PixelPosition) -> Option<&LayerSetting> {
+        self.layer_index(position)
+            .map(|layer| self.layer_settings.get(layer))
+    }
+
+    pub fn is_locked(&self, position: PixelPosition) -> bool {
+        self.layer_setting(position).is_some_and(|s| s.locked)
+    }
+
+    // The frame that marked pixels wrap around in the tiling mode
+    pub fn tiling_region(&self, app: &App) -> Option<PixelRegion> {
+        if !self.tiling {
+            return None;
+        }
+        let frame = self.camera.current_frame(app) as i16;
+        let layer = self.camera.current_layer(app) as i16;
+        let region = self.frame.get_base_region().shift_x(frame).shift_y(layer);
+        (!region.is_empty()).then_some(region)
+    }
+
+    pub fn drawing_color(&self) -> Rgba {
+        self.palette.restrict(self.color.get())
+    }
+
+    pub fn frame_duration(&self, frame: usize) -> Duration {
+        self.frame_durations
+            .get(frame)
+            .unwrap_or_else(|| self.animation.frame_interval())
+    }
 }
 
 impl Serialize for ConfigModel {
@@ -60,6 +112,14 @@ impl Serialize for ConfigModel {
         self.gesture.serialize(writer).or_fail()?;
         self.background_color.serialize(writer).or_fail()?;
         self.apng.serialize(writer).or_fail()?;
+        self.layer_settings.serialize(writer).or_fail()?;
+        self.frame_durations.serialize(writer).or_fail()?;
+        self.palette.serialize(writer).or_fail()?;
+        self.indexed_png.serialize(writer).or_fail()?;
+        self.pixel_perfect.serialize(writer).or_fail()?;
+        self.saved_undos.serialize(writer).or_fail()?;
+        self.tiling.serialize(writer).or_fail()?;
+        self.onion_skin.serialize(writer).or_fail()?;
         Ok(())
     }
 }
@@ -83,11 +143,19 @@ impl Deserialize for ConfigModel {
             gesture: Deserialize::deserialize_or_default(reader).or_fail()?,
             background_color: Deserialize::deserialize_or_default(reader).or_fail()?,
             apng: Deserialize::deserialize_or_default(reader).or_fail()?,
+            layer_settings: Deserialize::deserialize_or_default(reader).or_fail()?,
+            frame_durations: Deserialize::deserialize_or_default(reader).or_fail()?,
+            palette: Deserialize::deserialize_or_default(reader).or_fail()?,
+            indexed_png: Deserialize::deserialize_or_default(reader).or_fail()?,
+            pixel_perfect: Deserialize::deserialize_or_default(reader).or_fail()?,
+            saved_undos: Deserialize::deserialize_or_default(reader).or_fail()?,
+            tiling: Deserialize::deserialize_or_default(reader).or_fail()?,
+            onion_skin: Deserialize::deserialize_or_default(reader).or_fail()?,
         })
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
 pub struct Zoom(u8);
 
 impl Zoom {
@@ -134,7 +202,7 @@ impl Deserialize for Zoom {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct Camera(Position);
 
 impl Camera {
@@ -225,7 +293,7 @@ impl Deserialize for Camera {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct MinimumPixelSize(PixelSize);
 
 impl MinimumPixelSize {
@@ -309,7 +377,7 @@ impl Deserialize for MinimumPixelSize {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct DrawingColor(Rgba);
 
 impl DrawingColor {
@@ -341,7 +409,7 @@ impl Deserialize for DrawingColor {
 }
 
 // TODO: Rename s/FrameRegion/Frame/
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct FrameRegion {
     preview: bool,
     region: PixelRegion,
@@ -420,7 +488,7 @@ impl Deserialize for FrameRegion {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct FramePreviewScale(u8);
 
 impl FramePreviewScale {
@@ -453,7 +521,7 @@ impl Deserialize for FramePreviewScale {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct FramePreview(bool);
 
 impl FramePreview {
@@ -485,9 +553,22 @@ impl Deserialize for FramePreview {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct MaxUndos(u32);
 
+impl MaxUndos {
+    pub const MIN: u32 = 1;
+    pub const MAX: u32 = 1000;
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    pub fn set(&mut self, n: u32) {
+        self.0 = n.clamp(Self::MIN, Self::MAX);
+    }
+}
+
 impl Default for MaxUndos {
     fn default() -> Self {
         Self(100)
@@ -502,11 +583,123 @@ impl Serialize for MaxUndos {
 
 impl Deserialize for MaxUndos {
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
-        u32::deserialize(reader).map(Self).or_fail()
+        let n = u32::deserialize(reader).or_fail()?;
+        Ok(Self(n.clamp(Self::MIN, Self::MAX)))
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct SavedUndos(u16);
+
+impl SavedUndos {
+    pub const MAX: u16 = 100;
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    pub fn set(&mut self, n: u16) {
+        self.0 = n.min(Self::MAX);
+    }
+}
+
+impl Serialize for SavedUndos {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.0.serialize(writer).or_fail()
+    }
+}
+
+impl Deserialize for SavedUndos {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let n = u16::deserialize(reader).or_fail()?;
+        let mut v = Self::default();
+        v.set(n);
+        Ok(v)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct OnionSkin {
+    // The number of previous / next frames to be ghosted (0 means disabled)
+    frames: u8,
+
+    // The opacity (%) of the nearest ghost frames
+    opacity: u8,
+
+    // Tint previous frames red and next frames blue
+    pub tint: bool,
+}
+
+impl OnionSkin {
+    pub const MAX_FRAMES: u8 = 8;
+
+    pub const fn frames(self) -> u8 {
+        self.frames
+    }
+
+    pub fn set_frames(&mut self, n: u8) {
+        self.frames = n.min(Self::MAX_FRAMES);
+    }
+
+    pub const fn opacity(self) -> u8 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: u8) {
+        self.opacity = opacity.min(100);
+    }
+
+    // Returns the ghost color of `color` in the frame `distance` frames away from the current one
+    pub fn ghost(self, color: Rgba, distance: i16) -> Rgba {
+        let frames = u32::from(self.frames.max(1));
+        let falloff = frames + 1 - u32::from(distance.unsigned_abs()).min(frames);
+        let alpha = u32::from(color.a) * u32::from(self.opacity) * falloff / (100 * frames);
+        let mut ghost = color;
+        if self.tint {
+            let tint = if distance < 0 {
+                Rgba::new(255, 0, 0, 255)
+            } else {
+                Rgba::new(0, 0, 255, 255)
+            };
+            ghost.r = ((u16::from(color.r) + u16::from(tint.r)) / 2) as u8;
+            ghost.g = ((u16::from(color.g) + u16::from(tint.g)) / 2) as u8;
+            ghost.b = ((u16::from(color.b) + u16::from(tint.b)) / 2) as u8;
+        }
+        ghost.a = alpha as u8;
+        ghost
+    }
+}
+
+impl Default for OnionSkin {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            opacity: 30,
+            tint: true,
+        }
+    }
+}
+
+impl Serialize for OnionSkin {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.frames.serialize(writer).or_fail()?;
+        self.opacity.serialize(writer).or_fail()?;
+        self.tint.serialize(writer).or_fail()?;
+        Ok(())
+    }
+}
+
+impl Deserialize for OnionSkin {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut onion_skin = Self::default();
+        onion_skin.set_frames(u8::deserialize(reader).or_fail()?);
+        onion_skin.set_opacity(u8::deserialize(reader).or_fail()?);
+        onion_skin.tint = bool::deserialize(reader).or_fail()?;
+        Ok(onion_skin)
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
 pub struct Layer {
     enabled: bool,
     count: u16,
@@ -681,7 +874,199 @@ impl Deserialize for Layer {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct LayerSettings(Vec<LayerSetting>);
+
+impl LayerSettings {
+    pub fn get(&self, layer: usize) -> &LayerSetting {
+        static DEFAULT: LayerSetting = LayerSetting::DEFAULT;
+        self.0.get(layer).unwrap_or(&DEFAULT)
+    }
+
+    pub fn get_mut(&mut self, layer: usize) -> &mut LayerSetting {
+        if self.0.len() <= layer {
+            self.0.resize(layer + 1, LayerSetting::DEFAULT);
+        }
+        &mut self.0[layer]
+    }
+
+    // See `PixelCanvasModel::rearrange_layers()`
+    pub fn rearrange(&mut self, sources: &[Vec<usize>]) {
+        self.0 = sources
+            .iter()
+            .map(|layers| {
+                layers
+                    .first()
+                    .map_or(LayerSetting::DEFAULT, |&i| self.get(i).clone())
+            })
+            .collect();
+    }
+}
+
+impl Serialize for LayerSettings {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let n = u16::try_from(self.0.len()).or_fail()?;
+        n.serialize(writer).or_fail()?;
+        for setting in &self.0 {
+            let size = u16::try_from(setting.serialized_size().or_fail()?).or_fail()?;
+            size.serialize(writer).or_fail()?;
+            setting.serialize(writer).or_fail()?;
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for LayerSettings {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let n = u16::deserialize(reader).or_fail()?;
+        let mut settings = Vec::with_capacity(usize::from(n));
+        for _ in 0..n {
+            let size = u16::deserialize(reader).or_fail()?;
+            let mut reader = reader.take(u64::from(size));
+            settings.push(LayerSetting::deserialize(&mut reader).or_fail()?);
+            // Ignore unknown fields.
+            std::io::copy(&mut reader, &mut std::io::sink()).or_fail()?;
+        }
+        Ok(Self(settings))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LayerSetting {
+    pub name: String,
+    pub hidden: bool,
+    pub locked: bool,
+    opacity: u8,
+    pub blend_mode: BlendMode,
+}
+
+impl LayerSetting {
+    pub const MAX_OPACITY: u8 = 100;
+
+    const DEFAULT: Self = Self {
+        name: String::new(),
+        hidden: false,
+        locked: false,
+        opacity: Self::MAX_OPACITY,
+        blend_mode: BlendMode::Normal,
+    };
+
+    pub const fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: u8) {
+        self.opacity = opacity.min(Self::MAX_OPACITY);
+    }
+
+    pub fn blend(&self, mut src: Rgba, dst: Option<Rgba>) -> Option<Rgba> {
+        if self.hidden {
+            return dst;
+        }
+
+        if let Some(dst) = dst {
+            src = self.blend_mode.blend(src, dst);
+        }
+        src.a = (u16::from(src.a) * u16::from(self.opacity) / u16::from(Self::MAX_OPACITY)) as u8;
+        if src.a == 0 {
+            return dst;
+        }
+        Some(dst.map_or(src, |d| src.alpha_blend(d)))
+    }
+}
+
+impl Default for LayerSetting {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Serialize for LayerSetting {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let name = self.name.as_bytes();
+        u16::try_from(name.len())
+            .or_fail()?
+            .serialize(writer)
+            .or_fail()?;
+        writer.write_all(name).or_fail()?;
+        self.hidden.serialize(writer).or_fail()?;
+        self.locked.serialize(writer).or_fail()?;
+        self.opacity.serialize(writer).or_fail()?;
+        self.blend_mode.serialize(writer).or_fail()?;
+        Ok(())
+    }
+}
+
+impl Deserialize for LayerSetting {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let name_len = u16::deserialize(reader).or_fail()?;
+        let mut name = vec![0; usize::from(name_len)];
+        reader.read_exact(&mut name).or_fail()?;
+        Ok(Self {
+            name: String::from_utf8(name).or_fail()?,
+            hidden: Deserialize::deserialize(reader).or_fail()?,
+            locked: Deserialize::deserialize(reader).or_fail()?,
+            opacity: u8::deserialize(reader).or_fail()?.min(Self::MAX_OPACITY),
+            blend_mode: Deserialize::deserialize(reader).or_fail()?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Add,
+}
+
+impl BlendMode {
+    pub fn blend(self, src: Rgba, dst: Rgba) -> Rgba {
+        let f: fn(u32, u32) -> u32 = match self {
+            Self::Normal => return src,
+            Self::Multiply => |s: u32, d: u32| s * d / 255,
+            Self::Screen => |s: u32, d: u32| 255 - (255 - s) * (255 - d) / 255,
+            Self::Add => |s: u32, d: u32| (s + d).min(255),
+        };
+
+        // The blended color is mixed with the source color according to the backdrop alpha.
+        let a = u32::from(dst.a);
+        let mix = |s: u8, d: u8| {
+            let s = u32::from(s);
+            ((s * (255 - a) + f(s, u32::from(d)) * a) / 255) as u8
+        };
+        Rgba::new(
+            mix(src.r, dst.r),
+            mix(src.g, dst.g),
+            mix(src.b, dst.b),
+            src.a,
+        )
+    }
+}
+
+impl Serialize for BlendMode {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        (*self as u8).serialize(writer).or_fail()
+    }
+}
+
+impl Deserialize for BlendMode {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        match u8::deserialize(reader).or_fail()? {
+            0 => Ok(Self::Normal),
+            1 => Ok(Self::Multiply),
+            2 => Ok(Self::Screen),
+            3 => Ok(Self::Add),
+            n => Err(Failure::new(format!("unknown blend mode: {n}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Animation {
     enabled: bool,
     fps: u8,
@@ -757,8 +1142,169 @@ impl Deserialize for Animation {
     }
 }
 
+// Per-frame durations in milliseconds (`0` means that the frame follows the FPS setting).
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct FrameDurations(Vec<u16>);
+
+impl FrameDurations {
+    pub const MAX_MILLIS: u16 = 10000;
+
+    pub fn get(&self, frame: usize) -> Option<Duration> {
+        self.get_millis(frame)
+            .map(|ms| Duration::from_millis(u64::from(ms)))
+    }
+
+    pub fn get_millis(&self, frame: usize) -> Option<u16> {
+        self.0.get(frame).copied().filter(|&ms| ms != 0)
+    }
+
+    pub fn set_millis(&mut self, frame: usize, ms: u16) {
+        let ms = ms.min(Self::MAX_MILLIS);
+        if self.0.len() <= frame {
+            if ms == 0 {
+                return;
+            }
+            self.0.resize(frame + 1, 0);
+        }
+        self.0[frame] = ms;
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    // See `PixelCanvasModel::rearrange_frames()`
+    pub fn rearrange(&mut self, sources: &[Option<usize>]) {
+        self.0 = sources
+            .iter()
+            .map(|source| source.and_then(|i| self.0.get(i).copied()).unwrap_or(0))
+            .collect();
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+}
+
+impl Serialize for FrameDurations {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let n = u16::try_from(self.0.len()).or_fail()?;
+        n.serialize(writer).or_fail()?;
+        for ms in &self.0 {
+            ms.serialize(writer).or_fail()?;
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for FrameDurations {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let n = u16::deserialize(reader).or_fail()?;
+        let durations = (0..n)
+            .map(|_| u16::deserialize(reader).map(|ms| ms.min(Self::MAX_MILLIS)))
+            .collect::<Result<_>>()
+            .or_fail()?;
+        Ok(Self(durations))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct Palette {
+    enabled: bool,
+    colors: Vec<Rgba>,
+}
+
+impl Palette {
+    pub const MAX_COLORS: usize = 256;
+
+    pub const fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn colors(&self) -> &[Rgba] {
+        &self.colors
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.colors.len() >= Self::MAX_COLORS
+    }
+
+    pub fn add(&mut self, color: Rgba) -> bool {
+        if self.is_full() || self.colors.contains(&color) {
+            return false;
+        }
+        self.colors.push(color);
+        true
+    }
+
+    pub fn set_colors(&mut self, colors: impl IntoIterator<Item = Rgba>) {
+        self.colors.clear();
+        for color in colors {
+            self.add(color);
+        }
+    }
+
+    pub fn set(&mut self, index: usize, color: Rgba) {
+        if let Some(c) = self.colors.get_mut(index) {
+            *c = color;
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.colors.len() {
+            self.colors.remove(index);
+        }
+    }
+
+    pub fn restrict(&self, color: Rgba) -> Rgba {
+        if !self.enabled {
+            return color;
+        }
+
+        let distance = |c: &Rgba| {
+            let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+            d(c.r, color.r) + d(c.g, color.g) + d(c.b, color.b) + d(c.a, color.a)
+        };
+        self.colors
+            .iter()
+            .copied()
+            .min_by_key(distance)
+            .unwrap_or(color)
+    }
+}
+
+impl Serialize for Palette {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.enabled.serialize(writer).or_fail()?;
+        u16::try_from(self.colors.len())
+            .or_fail()?
+            .serialize(writer)
+            .or_fail()?;
+        for color in &self.colors {
+            color.serialize(writer).or_fail()?;
+        }
+        Ok(())
+    }
+}
+
+impl Deserialize for Palette {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        let enabled = bool::deserialize(reader).or_fail()?;
+        let n = u16::deserialize(reader).or_fail()?;
+        let mut colors = (0..n)
+            .map(|_| Rgba::deserialize(reader))
+            .collect::<Result<Vec<_>>>()
+            .or_fail()?;
+        colors.truncate(Self::MAX_COLORS);
+        Ok(Self { enabled, colors })
+    }
+}
+
 // TODO: Remove this struct in the future version
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct FingerMode {
     enabled: bool,
     cursor_distance: u32,
@@ -807,3 +1353,86 @@ impl Deserialize for FingerMode {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: Rgba = Rgba::new(200, 100, 50, 255);
+    const DST: Rgba = Rgba::new(100, 100, 100, 255);
+
+    #[test]
+    fn blend_mode_works() {
+        let cases = [
+            (BlendMode::Normal, Rgba::new(200, 100, 50, 255)),
+            (BlendMode::Multiply, Rgba::new(78, 39, 19, 255)),
+            (BlendMode::Screen, Rgba::new(222, 161, 131, 255)),
+            (BlendMode::Add, Rgba::new(255, 200, 150, 255)),
+        ];
+        for (mode, expected) in cases {
+            let setting = LayerSetting {
+                blend_mode: mode,
+                ..LayerSetting::DEFAULT
+            };
+            assert_eq!(setting.blend(SRC, Some(DST)), Some(expected), "{mode:?}");
+            assert_eq!(setting.blend(SRC, None), Some(SRC), "{mode:?}");
+
+            // The blended color fades into the source color as the backdrop becomes transparent
+            let transparent = Rgba::new(100, 100, 100, 0);
+            assert_eq!(mode.blend(SRC, transparent), SRC, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn layer_setting_blend_works() {
+        let setting = |opacity, hidden| {
+            let mut setting = LayerSetting {
+                hidden,
+                ..LayerSetting::DEFAULT
+            };
+            setting.set_opacity(opacity);
+            setting
+        };
+        let half = Rgba::new(200, 100, 50, 127);
+        let cases = [
+            (setting(100, false), Some(DST), Some(SRC)),
+            (setting(100, false), None, Some(SRC)),
+            (setting(50, false), Some(DST), Some(half.alpha_blend(DST))),
+            (setting(50, false), None, Some(half)),
+            (setting(0, false), Some(DST), Some(DST)),
+            (setting(0, false), None, None),
+            (setting(100, true), Some(DST), Some(DST)),
+            (setting(100, true), None, None),
+            (setting(200, false), None, Some(SRC)),
+        ];
+        for (i, (setting, dst, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(setting.blend(SRC, dst), expected, "case {i}");
+        }
+    }
+
+    #[test]
+    fn palette_deserialize_truncates_colors() -> Result<()> {
+        let palette = Palette {
+            enabled: true,
+            colors: (0..Palette::MAX_COLORS + 2)
+                .map(|i| Rgba::new(i as u8, (i / 256) as u8, 0, 255))
+                .collect(),
+        };
+        let mut buf = Vec::new();
+        palette.serialize(&mut buf).or_fail()?;
+
+        let loaded = Palette::deserialize(&mut buf.as_slice()).or_fail()?;
+        assert!(loaded.is_enabled());
+        assert_eq!(loaded.colors(), &palette.colors()[..Palette::MAX_COLORS]);
+        Ok(())
+    }
+
+    #[test]
+    fn saved_undos_deserialize_clamps_value() -> Result<()> {
+        let mut buf = Vec::new();
+        (SavedUndos::MAX + 1).serialize(&mut buf).or_fail()?;
+        let v = SavedUndos::deserialize(&mut buf.as_slice()).or_fail()?;
+        assert_eq!(v.get(), SavedUndos::MAX);
+        Ok(())
+    }
+}
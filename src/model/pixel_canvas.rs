@@ -1,4 +1,7 @@
-use super::config::{ConfigModel, FrameRegion, Layer};
+use super::{
+    config::{ConfigModel, FrameDurations, FrameRegion, Layer, LayerSettings},
+    tool::ToolKind,
+};
 use crate::{
     pixel::{Pixel, PixelPosition, PixelRegion},
     serialize::{Deserialize, Serialize},
@@ -6,7 +9,7 @@ use crate::{
 use orfail::{OrFail, Result};
 use pagurus::image::Rgba;
 use std::{
-    collections::{BTreeMap, BTreeSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     io::{Read, Write},
 };
 
@@ -17,7 +20,9 @@ pub struct PixelCanvasModel {
     pixels: Pixels,
 
     // The following fields are not serialized / deserialized
+    command_branches: Vec<CommandBranch>,
     dirty_positions: BTreeSet<PixelPosition>,
+    composited: CompositedCache,
     state_version: i64,
 }
 
@@ -32,7 +37,9 @@ impl PixelCanvasModel {
         pixels: impl Iterator<Item = Pixel>,
     ) -> Result<()> {
         let mut command = PixelCanvasCommand::new();
-        command.draw = pixels.collect();
+        command.draw = pixels
+            .filter(|pixel| !config.is_locked(pixel.position))
+            .collect();
         command.draw.sort_by_key(|x| x.position);
         for pixel in &mut command.draw {
             if let Some(color) = self.pixels.get_pixel(pixel.position) {
@@ -50,7 +57,7 @@ impl PixelCanvasModel {
         pixels: impl Iterator<Item = PixelPosition>,
     ) -> Result<()> {
         let mut command = PixelCanvasCommand::default();
-        for position in pixels {
+        for position in pixels.filter(|p| !config.is_locked(*p)) {
             if let Some(color) = self.pixels.get_pixel(position) {
                 command.erase.push(Pixel::new(position, color));
             }
@@ -67,7 +74,7 @@ impl PixelCanvasModel {
         draw_pixels: impl Iterator<Item = Pixel>,
     ) -> Result<()> {
         let mut command = PixelCanvasCommand::default();
-        for position in erase_pixels {
+        for position in erase_pixels.filter(|p| !config.is_locked(*p)) {
             if let Some(color) = self.pixels.get_pixel(position) {
                 command.erase.push(Pixel::new(position, color));
             }
@@ -75,7 +82,7 @@ impl PixelCanvasModel {
         command.erase.sort_by_key(|x| x.position);
 
         let mut overwritten = Vec::new();
-        for pixel in draw_pixels {
+        for pixel in draw_pixels.filter(|p| !config.is_locked(p.position)) {
             let color = if command
                 .erase
                 .binary_search_by_key(&pixel.position, |x| x.position)
@@ -103,8 +110,8 @@ impl PixelCanvasModel {
         // TODO: optimize (e.g., to use cache to get target pixels)
 
         let mut command = PixelCanvasCommand::default();
-        for (&position, &color) in &self.pixels.pixels {
-            if color != old {
+        for (position, color) in self.pixels.iter() {
+            if color != old || config.is_locked(position) {
                 continue;
             }
 
@@ -118,20 +125,143 @@ impl PixelCanvasModel {
         Ok(())
     }
 
+    // Moves the pixels of every layer so that the frame `i` has the pixels of the frame `sources[i]`
+    // (or no pixels if it's `None`). Frames beyond `sources` become empty.
+    pub fn rearrange_frames(
+        &mut self,
+        config: &mut ConfigModel,
+        sources: &[Option<usize>],
+    ) -> Result<()> {
+        let frame = config.frame.get_base_region();
+        let width = frame.size().width as i16;
+
+        let mut command = PixelCanvasCommand::default();
+        for (position, color) in self.pixels.iter() {
+            if position.y < frame.start.y {
+                continue;
+            }
+            let Ok(index) = usize::try_from((position.x - frame.start.x).div_euclid(width)) else {
+                continue;
+            };
+            if sources.get(index) != Some(&Some(index)) {
+                command.erase.push(Pixel::new(position, color));
+            }
+            for (target, _) in sources
+                .iter()
+                .enumerate()
+                .filter(|&(target, source)| *source == Some(index) && target != index)
+            {
+                let delta = (target as i16 - index as i16) * width;
+                let x = position.x.checked_add(delta).or_fail()?;
+                command
+                    .draw
+                    .push(Pixel::new(PixelPosition::from_xy(x, position.y), color));
+            }
+        }
+        command.erase.sort_by_key(|x| x.position);
+        command.draw.sort_by_key(|x| x.position);
+
+        let before = CanvasLayout::new(config);
+        config.frame_durations.rearrange(sources);
+        command.layout = Some(Box::new(LayoutChange {
+            before,
+            after: CanvasLayout::new(config),
+        }));
+        self.apply_command(config, command).or_fail()?;
+        Ok(())
+    }
+
+    // Moves the pixels of every frame so that the layer `i` has the pixels of the layers `sources[i]`
+    // composited in order with their layer settings (the first one is copied as it is).
+    // Hidden layers are composited as if they were visible so that merging never loses pixels.
+    // Layers beyond `sources` become empty.
+    pub fn rearrange_layers(
+        &mut self,
+        config: &mut ConfigModel,
+        sources: &[Vec<usize>],
+    ) -> Result<()> {
+        let frame = config.frame.get_base_region();
+        let height = frame.size().height as i16;
+        let layer_index = |position: PixelPosition| {
+            if position.x < frame.start.x {
+                return None;
+            }
+            usize::try_from((position.y - frame.start.y).div_euclid(height)).ok()
+        };
+        let is_unchanged = |layer: usize| sources.get(layer).is_some_and(|s| *s == [layer]);
+
+        let mut command = PixelCanvasCommand::default();
+        let mut buckets = HashMap::<_, Vec<_>>::new();
+        for (position, color) in self.pixels.iter() {
+            let Some(layer) = layer_index(position) else {
+                continue;
+            };
+            if !is_unchanged(layer) {
+                command.erase.push(Pixel::new(position, color));
+            }
+            buckets.entry(layer).or_default().push((position, color));
+        }
+        command.erase.sort_by_key(|x| x.position);
+
+        let mut colors = BTreeMap::new();
+        for (target, layers) in sources.iter().enumerate() {
+            if is_unchanged(target) {
+                continue;
+            }
+            for (i, &source) in layers.iter().enumerate() {
+                let mut setting = config.layer_settings.get(source).clone();
+                setting.hidden = false;
+                let delta = (target as i16 - source as i16) * height;
+                for &(position, color) in buckets.get(&source).into_iter().flatten() {
+                    let y = position.y.checked_add(delta).or_fail()?;
+                    let position = PixelPosition::from_xy(position.x, y);
+                    let color = if i == 0 {
+                        Some(color)
+                    } else {
+                        setting.blend(color, colors.get(&position).copied())
+                    };
+                    if let Some(color) = color {
+                        colors.insert(position, color);
+                    } else {
+                        colors.remove(&position);
+                    }
+                }
+            }
+        }
+        command.draw = colors
+            .into_iter()
+            .map(|(position, color)| Pixel::new(position, color))
+            .collect();
+
+        let before = CanvasLayout::new(config);
+        config.layer_settings.rearrange(sources);
+        config.layer.set_count(sources.len() as u16);
+        command.layout = Some(Box::new(LayoutChange {
+            before,
+            after: CanvasLayout::new(config),
+        }));
+        self.apply_command(config, command).or_fail()?;
+        Ok(())
+    }
+
     fn apply_command(&mut self, config: &ConfigModel, command: PixelCanvasCommand) -> Result<()> {
-        if command.erase.is_empty() && command.draw.is_empty() {
+        let layout_changed = command
+            .layout
+            .as_ref()
+            .is_some_and(|layout| layout.before != layout.after);
+        if command.erase.is_empty() && command.draw.is_empty() && !layout_changed {
             return Ok(());
         }
 
-        self.command_log.truncate(self.command_log_tail);
+        self.stash_redo_commands();
         self.command_log.push_back(command);
-        self.redo_command(config).or_fail()?;
+        self.redo_pixels(config).or_fail()?;
 
         Ok(())
     }
 
     pub fn region(&self) -> PixelRegion {
-        PixelRegion::from_positions(self.pixels.pixels.keys().copied())
+        PixelRegion::from_positions(self.pixels.iter().map(|(p, _)| p))
     }
 
     pub fn get_layers(&self, config: &ConfigModel) -> u16 {
@@ -146,28 +276,77 @@ impl PixelCanvasModel {
         (right / width) + 1
     }
 
-    pub fn get_pixels(
-        &self,
-        config: &ConfigModel,
+    pub fn get_pixels<'a>(
+        &'a self,
+        config: &'a ConfigModel,
         region: PixelRegion,
-    ) -> impl '_ + Iterator<Item = Pixel> {
-        // TODO: optimize (e.g., use cache to avoid redundant calculation)
-        let frame = config.frame;
-        let layer = config.layer;
-        let frame_count = config.animation.enabled_frame_count();
+    ) -> impl 'a + Iterator<Item = Pixel> {
+        let cache = self.composited.is_valid(config).then_some(&self.composited);
         region.pixels().filter_map(move |position| {
-            self.get_layered_pixel(frame, frame_count, layer, position)
+            cache
+                .and_then(|cache| cache.get(position))
+                .unwrap_or_else(|| self.get_pixel(config, position))
                 .map(|color| Pixel::new(position, color))
         })
     }
 
+    // Composites the pixels in `region` in advance so that `get_pixels()` doesn't need to
+    // blend layers again until the pixels are modified or the layer configuration is changed
+    pub fn cache_composited_pixels(&mut self, config: &ConfigModel, region: PixelRegion) {
+        if region.is_empty() {
+            return;
+        }
+        if !self.composited.is_valid(config) {
+            self.composited = CompositedCache {
+                key: Some(CompositedKey::new(config)),
+                tiles: HashMap::new(),
+            };
+        }
+
+        let ((start_y, start_x), _) = tile_index(region.start);
+        let ((end_y, end_x), _) = tile_index(region.end.move_x(-1).move_y(-1));
+        let tile_count = (end_y - start_y + 1) as usize * (end_x - start_x + 1) as usize;
+        if tile_count > MAX_COMPOSITED_TILES {
+            return;
+        }
+        if self.composited.tiles.len() + tile_count > MAX_COMPOSITED_TILES {
+            self.composited.tiles.retain(|&(y, x), _| {
+                (start_y..=end_y).contains(&y) && (start_x..=end_x).contains(&x)
+            });
+        }
+
+        for y in start_y..=end_y {
+            for x in start_x..=end_x {
+                if self.composited.tiles.contains_key(&(y, x)) {
+                    continue;
+                }
+                let mut colors = None;
+                for offset in 0..TILE_AREA {
+                    let position = PixelPosition::from_xy(
+                        (x << TILE_SHIFT) + (offset as i16 & (TILE_SIZE - 1)),
+                        (y << TILE_SHIFT) + (offset >> TILE_SHIFT) as i16,
+                    );
+                    if let Some(color) = self.get_pixel(config, position) {
+                        colors.get_or_insert_with(|| Box::new([None; TILE_AREA]))[offset] =
+                            Some(color);
+                    }
+                }
+                self.composited.tiles.insert((y, x), colors);
+            }
+        }
+    }
+
     pub fn get_pixel(&self, config: &ConfigModel, position: PixelPosition) -> Option<Rgba> {
-        self.get_layered_pixel(
-            config.frame,
-            config.animation.enabled_frame_count(),
-            config.layer,
-            position,
-        )
+        let frame_count = config.animation.enabled_frame_count();
+        let mut color = None;
+        config
+            .layer
+            .for_each_lower_layer_pixel(config.frame, frame_count, position, |position| {
+                if let Some(c) = self.pixels.get_pixel(position) {
+                    color = blend_layer_pixel(config, position, c, color);
+                }
+            });
+        color
     }
 
     pub fn get_pixel_with_alpha(
@@ -182,7 +361,7 @@ impl PixelCanvasModel {
         let mut color = None;
         layer.for_each_lower_layer_pixel_but_last(frame, frame_count, position, |position| {
             if let Some(c) = self.pixels.get_pixel(position) {
-                color = Some(color.map_or(c, |d| c.alpha_blend(d)));
+                color = blend_layer_pixel(config, position, c, color);
             }
         });
 
@@ -192,7 +371,7 @@ impl PixelCanvasModel {
 
         if let Some(mut c) = self.get_direct_pixel(position) {
             c.a = alpha;
-            Some(color.map_or(c, |d| c.alpha_blend(d)))
+            blend_layer_pixel(config, position, c, color)
         } else {
             color
         }
@@ -203,26 +382,10 @@ impl PixelCanvasModel {
     }
 
     pub fn raw_pixels(&self) -> impl '_ + Iterator<Item = (PixelPosition, Rgba)> {
-        self.pixels.pixels.iter().map(|(&p, &c)| (p, c))
+        self.pixels.iter()
     }
 
-    fn get_layered_pixel(
-        &self,
-        frame: FrameRegion,
-        frame_count: u16,
-        layer: Layer,
-        position: PixelPosition,
-    ) -> Option<Rgba> {
-        let mut color = None;
-        layer.for_each_lower_layer_pixel(frame, frame_count, position, |position| {
-            if let Some(c) = self.pixels.get_pixel(position) {
-                color = Some(color.map_or(c, |d| c.alpha_blend(d)));
-            }
-        });
-        color
-    }
-
-    pub fn undo_command(&mut self, config: &ConfigModel) -> Result<()> {
+    pub fn undo_command(&mut self, config: &mut ConfigModel) -> Result<()> {
         if let Some(i) = self.command_log_tail.checked_sub(1) {
             let layer = config.layer;
             let frame = config.frame;
@@ -232,21 +395,36 @@ impl PixelCanvasModel {
                 self.pixels.erase_pixel(pixel).or_fail()?;
                 layer.for_each_upper_layer_pixel(frame, frame_count, pixel.position, |position| {
                     self.dirty_positions.insert(position);
+                    self.composited.invalidate(position);
                 });
             }
             for &pixel in &command.erase {
                 self.pixels.draw_pixel(pixel).or_fail()?;
                 layer.for_each_upper_layer_pixel(frame, frame_count, pixel.position, |position| {
                     self.dirty_positions.insert(position);
+                    self.composited.invalidate(position);
                 });
             }
+            if let Some(layout) = &command.layout {
+                layout.before.apply(config);
+            }
             self.command_log_tail = i;
         }
         self.state_version -= 1;
         Ok(())
     }
 
-    pub fn redo_command(&mut self, config: &ConfigModel) -> Result<()> {
+    pub fn redo_command(&mut self, config: &mut ConfigModel) -> Result<()> {
+        let tail = self.command_log_tail;
+        self.redo_pixels(config).or_fail()?;
+        if let Some(layout) = self.command_log.get(tail).and_then(|c| c.layout.as_ref()) {
+            layout.after.apply(config);
+        }
+        Ok(())
+    }
+
+    // The config changes of the command should be applied by the caller
+    fn redo_pixels(&mut self, config: &ConfigModel) -> Result<()> {
         if let Some(command) = self.command_log.get(self.command_log_tail) {
             let layer = config.layer;
             let frame = config.frame;
@@ -255,12 +433,14 @@ impl PixelCanvasModel {
                 self.pixels.erase_pixel(pixel).or_fail()?;
                 layer.for_each_upper_layer_pixel(frame, frame_count, pixel.position, |position| {
                     self.dirty_positions.insert(position);
+                    self.composited.invalidate(position);
                 });
             }
             for &pixel in &command.draw {
                 self.pixels.draw_pixel(pixel).or_fail()?;
                 layer.for_each_upper_layer_pixel(frame, frame_count, pixel.position, |position| {
                     self.dirty_positions.insert(position);
+                    self.composited.invalidate(position);
                 });
             }
             self.command_log_tail += 1;
@@ -273,9 +453,161 @@ impl PixelCanvasModel {
         if self.command_log_tail > 0 {
             self.command_log.pop_front();
             self.command_log_tail -= 1;
+
+            // Branches forked from the forgotten state are no longer reachable
+            self.command_branches.retain(|b| b.base > 0);
+            for branch in &mut self.command_branches {
+                branch.shift_base(1);
+            }
+        }
+    }
+
+    fn stash_redo_commands(&mut self) {
+        let base = self.command_log_tail;
+        if base == self.command_log.len() {
+            return;
+        }
+
+        let commands = self.command_log.drain(base..).collect();
+        let (branches, others) = std::mem::take(&mut self.command_branches)
+            .into_iter()
+            .partition(|b| b.base > base);
+        self.command_branches = others;
+        self.command_branches.push(CommandBranch {
+            base,
+            commands,
+            branches,
+        });
+    }
+
+    pub fn branch_count(&self, state: usize) -> usize {
+        self.command_branches
+            .iter()
+            .filter(|b| b.base == state)
+            .count()
+    }
+
+    pub fn switch_branch(&mut self, config: &mut ConfigModel, state: usize) -> Result<()> {
+        let Some(i) = self.command_branches.iter().position(|b| b.base == state) else {
+            return Ok(());
+        };
+        self.jump_to_command(config, state).or_fail()?;
+
+        let branch = self.command_branches.remove(i);
+        self.stash_redo_commands();
+        self.command_log.extend(branch.commands);
+        self.command_branches.extend(branch.branches);
+        Ok(())
+    }
+
+    pub fn set_last_command_tool(&mut self, tool: ToolKind) {
+        if let Some(i) = self.command_log_tail.checked_sub(1) {
+            self.command_log[i].tool = Some(tool);
         }
     }
 
+    pub fn jump_to_command(&mut self, config: &mut ConfigModel, tail: usize) -> Result<()> {
+        let tail = tail.min(self.command_log.len());
+        while self.command_log_tail > tail {
+            self.undo_command(config).or_fail()?;
+        }
+        while self.command_log_tail < tail {
+            self.redo_command(config).or_fail()?;
+        }
+        Ok(())
+    }
+
+    pub fn coalesce_commands(&mut self, start: usize) {
+        if start + 1 >= self.command_log_tail {
+            return;
+        }
+
+        let mut changes = BTreeMap::new();
+        let mut layout: Option<Box<LayoutChange>> = None;
+        for command in self.command_log.drain(start..self.command_log_tail) {
+            if let Some(next) = command.layout {
+                layout = Some(match layout {
+                    Some(mut layout) => {
+                        layout.after = next.after;
+                        layout
+                    }
+                    None => next,
+                });
+            }
+
+            let mut command_changes = BTreeMap::new();
+            for pixel in command.erase {
+                command_changes.insert(pixel.position, (Some(pixel.color), None));
+            }
+            for pixel in command.draw {
+                command_changes
+                    .entry(pixel.position)
+                    .or_insert((None, None))
+                    .1 = Some(pixel.color).filter(|c| c.a != 0);
+            }
+            for (position, (before, after)) in command_changes {
+                changes.entry(position).or_insert((before, after)).1 = after;
+            }
+        }
+
+        let mut command = PixelCanvasCommand::new();
+        command.layout = layout;
+        for (position, (before, after)) in changes {
+            if before == after {
+                continue;
+            }
+            if let Some(color) = before {
+                command.erase.push(Pixel::new(position, color));
+            }
+            if let Some(color) = after {
+                command.draw.push(Pixel::new(position, color));
+            }
+        }
+        self.command_log.insert(start, command);
+
+        let removed = self.command_log_tail - start - 1;
+        self.command_branches
+            .retain(|b| b.base <= start || b.base >= self.command_log_tail);
+        for branch in &mut self.command_branches {
+            if branch.base >= self.command_log_tail {
+                branch.shift_base(removed);
+            }
+        }
+        self.command_log_tail = start + 1;
+    }
+
+    pub fn enforce_command_log_budget(&mut self, max_commands: usize, max_bytes: usize) {
+        while self.command_log_tail > max_commands {
+            self.forget_oldest_command();
+        }
+
+        let mut usage = self.command_log_memory_usage();
+        while usage > max_bytes && !self.command_branches.is_empty() {
+            usage -= self.command_branches.remove(0).memory_usage();
+        }
+        while usage > max_bytes && self.command_log.len() > 1 {
+            let command = if self.command_log_tail > 0 {
+                self.command_log_tail -= 1;
+                self.command_log.pop_front()
+            } else {
+                self.command_log.pop_back()
+            };
+            usage -= command.map_or(0, |c| c.memory_usage());
+        }
+    }
+
+    pub fn command_log_memory_usage(&self) -> usize {
+        self.command_log
+            .iter()
+            .map(|c| c.memory_usage())
+            .sum::<usize>()
+            + self
+                .command_branches
+                .iter()
+                .map(|b| b.memory_usage())
+                .sum::<usize>()
+    }
+
     pub fn command_log_tail(&self) -> usize {
         self.command_log_tail
     }
@@ -293,43 +625,255 @@ impl PixelCanvasModel {
     }
 }
 
-impl Serialize for PixelCanvasModel {
-    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+fn blend_layer_pixel(
+    config: &ConfigModel,
+    position: PixelPosition,
+    src: Rgba,
+    dst: Option<Rgba>,
+) -> Option<Rgba> {
+    if let Some(setting) = config.layer_setting(position) {
+        setting.blend(src, dst)
+    } else {
+        Some(dst.map_or(src, |d| src.alpha_blend(d)))
+    }
+}
+
+impl PixelCanvasModel {
+    pub fn serialize_with_command_log<W: Write>(
+        &self,
+        writer: &mut W,
+        max_commands: usize,
+    ) -> Result<()> {
         let mut writer = libflate::deflate::Encoder::new(writer);
 
-        // Don't serializing the command log
-        0usize.serialize(&mut writer).or_fail()?;
-        0usize.serialize(&mut writer).or_fail()?;
+        // Only the most recent undo-able commands are saved (redo-able ones are dropped)
+        let n = max_commands.min(self.command_log_tail);
+        let commands = self
+            .command_log
+            .range(self.command_log_tail - n..self.command_log_tail);
+        n.serialize(&mut writer).or_fail()?;
+        for command in commands.clone() {
+            command.serialize(&mut writer).or_fail()?;
+        }
+        n.serialize(&mut writer).or_fail()?;
 
         self.pixels.serialize(&mut writer).or_fail()?;
+
+        // The tools of the commands are stored after the pixels (older versions ignore them)
+        let tags = commands
+            .clone()
+            .map(|command| command.tool.map_or(0, |tool| tool as u8 + 1))
+            .collect::<Vec<_>>();
+        tags.len().serialize(&mut writer).or_fail()?;
+        writer.write_all(&tags).or_fail()?;
+
+        // Followed by the layout changes of the commands (each one is prefixed by its size)
+        let mut layouts = Vec::new();
+        for command in commands {
+            if let Some(layout) = &command.layout {
+                1u8.serialize(&mut layouts).or_fail()?;
+                layout
+                    .serialized_size()
+                    .or_fail()?
+                    .serialize(&mut layouts)
+                    .or_fail()?;
+                layout.serialize(&mut layouts).or_fail()?;
+            } else {
+                0u8.serialize(&mut layouts).or_fail()?;
+            }
+        }
+        layouts.len().serialize(&mut writer).or_fail()?;
+        writer.write_all(&layouts).or_fail()?;
         writer.finish().into_result().or_fail()?;
         Ok(())
     }
 }
 
+impl Serialize for PixelCanvasModel {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.serialize_with_command_log(writer, 0).or_fail()
+    }
+}
+
 impl Deserialize for PixelCanvasModel {
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
         let mut reader = libflate::deflate::Decoder::new(reader);
+        let mut command_log: VecDeque<PixelCanvasCommand> =
+            Deserialize::deserialize(&mut reader).or_fail()?;
+        let command_log_tail = usize::deserialize(&mut reader).or_fail()?;
+        (command_log_tail <= command_log.len()).or_fail()?;
+        let pixels = Deserialize::deserialize(&mut reader).or_fail()?;
+
+        // Files saved by older versions have no trailer
+        let mut trailer = Vec::new();
+        reader.read_to_end(&mut trailer).or_fail()?;
+        if !trailer.is_empty() {
+            let mut trailer = trailer.as_slice();
+            let n = usize::deserialize(&mut trailer).or_fail()?;
+            let tags = trailer.get(..n).or_fail()?;
+            for (command, &tag) in command_log.iter_mut().zip(tags) {
+                // Unknown tools (added by newer versions) are ignored
+                command.tool = tag
+                    .checked_sub(1)
+                    .and_then(|kind| ToolKind::deserialize(&mut [kind].as_slice()).ok());
+            }
+            trailer = &trailer[n..];
+
+            if !trailer.is_empty() {
+                let n = usize::deserialize(&mut trailer).or_fail()?;
+                let mut layouts = trailer.get(..n).or_fail()?;
+                for command in &mut command_log {
+                    if !bool::deserialize(&mut layouts).or_fail()? {
+                        continue;
+                    }
+                    let size = usize::deserialize(&mut layouts).or_fail()?;
+                    let mut reader = layouts.get(..size).or_fail()?;
+                    command.layout =
+                        Some(Box::new(LayoutChange::deserialize(&mut reader).or_fail()?));
+                    layouts = &layouts[size..];
+                }
+            }
+        }
+
         Ok(Self {
-            command_log: Deserialize::deserialize(&mut reader).or_fail()?,
-            command_log_tail: Deserialize::deserialize(&mut reader).or_fail()?,
-            pixels: Deserialize::deserialize(&mut reader).or_fail()?,
+            command_log,
+            command_log_tail,
+            pixels,
+            command_branches: Vec::new(),
             dirty_positions: Default::default(),
+            composited: Default::default(),
             state_version: 0,
         })
     }
 }
 
+// Redo commands that were abandoned by a new command (forked at the state `base`)
+#[derive(Debug)]
+struct CommandBranch {
+    base: usize,
+    commands: Vec<PixelCanvasCommand>,
+    branches: Vec<CommandBranch>,
+}
+
+impl CommandBranch {
+    fn shift_base(&mut self, n: usize) {
+        self.base -= n;
+        for branch in &mut self.branches {
+            branch.shift_base(n);
+        }
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.commands
+            .iter()
+            .map(|c| c.memory_usage())
+            .sum::<usize>()
+            + self
+                .branches
+                .iter()
+                .map(|b| b.memory_usage())
+                .sum::<usize>()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct PixelCanvasCommand {
     pub erase: Vec<Pixel>,
     pub draw: Vec<Pixel>,
+    pub tool: Option<ToolKind>,
+    pub layout: Option<Box<LayoutChange>>,
 }
 
 impl PixelCanvasCommand {
     fn new() -> Self {
         Self::default()
     }
+
+    pub fn positions(&self) -> impl '_ + Iterator<Item = PixelPosition> {
+        self.erase
+            .iter()
+            .chain(self.draw.iter())
+            .map(|pixel| pixel.position)
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.positions().collect::<BTreeSet<_>>().len()
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + (self.erase.capacity() + self.draw.capacity()) * std::mem::size_of::<Pixel>()
+            + self
+                .layout
+                .as_ref()
+                .map_or(0, |_| std::mem::size_of::<LayoutChange>())
+    }
+}
+
+// Config values that frame / layer operations change together with the pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanvasLayout {
+    pub frame_durations: FrameDurations,
+    pub layer_settings: LayerSettings,
+    pub layer_count: u16,
+}
+
+impl CanvasLayout {
+    pub fn new(config: &ConfigModel) -> Self {
+        Self {
+            frame_durations: config.frame_durations.clone(),
+            layer_settings: config.layer_settings.clone(),
+            layer_count: config.layer.count(),
+        }
+    }
+
+    fn apply(&self, config: &mut ConfigModel) {
+        config.frame_durations = self.frame_durations.clone();
+        config.layer_settings = self.layer_settings.clone();
+        config.layer.set_count(self.layer_count);
+    }
+}
+
+impl Serialize for CanvasLayout {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.frame_durations.serialize(writer).or_fail()?;
+        self.layer_settings.serialize(writer).or_fail()?;
+        self.layer_count.serialize(writer).or_fail()?;
+        Ok(())
+    }
+}
+
+impl Deserialize for CanvasLayout {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Self {
+            frame_durations: Deserialize::deserialize(reader).or_fail()?,
+            layer_settings: Deserialize::deserialize(reader).or_fail()?,
+            layer_count: Deserialize::deserialize(reader).or_fail()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutChange {
+    pub before: CanvasLayout,
+    pub after: CanvasLayout,
+}
+
+impl Serialize for LayoutChange {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.before.serialize(writer).or_fail()?;
+        self.after.serialize(writer).or_fail()?;
+        Ok(())
+    }
+}
+
+impl Deserialize for LayoutChange {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        Ok(Self {
+            before: Deserialize::deserialize(reader).or_fail()?,
+            after: Deserialize::deserialize(reader).or_fail()?,
+        })
+    }
 }
 
 impl Serialize for PixelCanvasCommand {
@@ -368,13 +912,98 @@ impl Deserialize for PixelCanvasCommand {
             .copied()
             .map(|pos| Ok(Pixel::new(pos, Rgba::deserialize(reader).or_fail()?)))
             .collect::<Result<Vec<_>>>()?;
-        Ok(Self { erase, draw })
+        Ok(Self {
+            erase,
+            draw,
+            tool: None,
+            layout: None,
+        })
+    }
+}
+
+const TILE_SHIFT: u32 = 5;
+const TILE_SIZE: i16 = 1 << TILE_SHIFT;
+const TILE_AREA: usize = (TILE_SIZE * TILE_SIZE) as usize;
+
+// (y, x) of a tile
+type TileIndex = (i16, i16);
+
+fn tile_index(position: PixelPosition) -> (TileIndex, usize) {
+    let index = (position.y >> TILE_SHIFT, position.x >> TILE_SHIFT);
+    let mask = TILE_SIZE - 1;
+    let offset = (((position.y & mask) << TILE_SHIFT) | (position.x & mask)) as usize;
+    (index, offset)
+}
+
+// A fixed-size block of pixels (a pixel with zero alpha is treated as empty)
+#[derive(Debug)]
+struct Tile {
+    colors: Box<[Rgba; TILE_AREA]>,
+    count: usize,
+}
+
+impl Default for Tile {
+    fn default() -> Self {
+        Self {
+            colors: Box::new([Rgba::new(0, 0, 0, 0); TILE_AREA]),
+            count: 0,
+        }
+    }
+}
+
+const MAX_COMPOSITED_TILES: usize = 1024;
+
+#[derive(Debug)]
+struct CompositedKey {
+    frame: FrameRegion,
+    frame_count: u16,
+    layer: Layer,
+    layer_settings: LayerSettings,
+}
+
+impl CompositedKey {
+    fn new(config: &ConfigModel) -> Self {
+        Self {
+            frame: config.frame,
+            frame_count: config.animation.enabled_frame_count(),
+            layer: config.layer,
+            layer_settings: config.layer_settings.clone(),
+        }
+    }
+}
+
+// Layer-blended colors of tiles (`None` means that the tile has no visible pixels)
+#[derive(Debug, Default)]
+struct CompositedCache {
+    key: Option<CompositedKey>,
+    tiles: HashMap<TileIndex, Option<Box<[Option<Rgba>; TILE_AREA]>>>,
+}
+
+impl CompositedCache {
+    fn is_valid(&self, config: &ConfigModel) -> bool {
+        self.key.as_ref().is_some_and(|key| {
+            key.frame == config.frame
+                && key.frame_count == config.animation.enabled_frame_count()
+                && key.layer == config.layer
+                && key.layer_settings == config.layer_settings
+        })
+    }
+
+    fn get(&self, position: PixelPosition) -> Option<Option<Rgba>> {
+        let (index, offset) = tile_index(position);
+        let colors = self.tiles.get(&index)?;
+        Some(colors.as_ref().and_then(|colors| colors[offset]))
+    }
+
+    fn invalidate(&mut self, position: PixelPosition) {
+        self.tiles.remove(&tile_index(position).0);
     }
 }
 
 #[derive(Debug, Default)]
 struct Pixels {
-    pixels: BTreeMap<PixelPosition, Rgba>,
+    tiles: HashMap<TileIndex, Tile>,
+    len: usize,
     non_neg_right_bottom: PixelPosition,
     non_neg_ys: BTreeMap<i16, usize>,
     non_neg_xs: BTreeMap<i16, usize>,
@@ -382,14 +1011,60 @@ struct Pixels {
 
 impl Pixels {
     fn get_pixel(&self, position: PixelPosition) -> Option<Rgba> {
-        self.pixels.get(&position).copied()
+        let (index, offset) = tile_index(position);
+        let color = self.tiles.get(&index)?.colors[offset];
+        (color.a != 0).then_some(color)
+    }
+
+    fn iter(&self) -> impl '_ + Iterator<Item = (PixelPosition, Rgba)> {
+        let mut indices = self.tiles.keys().copied().collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices.into_iter().flat_map(move |(ty, tx)| {
+            self.tiles[&(ty, tx)]
+                .colors
+                .iter()
+                .enumerate()
+                .filter(|(_, color)| color.a != 0)
+                .map(move |(offset, &color)| {
+                    let y = (ty << TILE_SHIFT) + (offset >> TILE_SHIFT) as i16;
+                    let x = (tx << TILE_SHIFT) + (offset as i16 & (TILE_SIZE - 1));
+                    (PixelPosition::from_xy(x, y), color)
+                })
+        })
+    }
+
+    fn insert(&mut self, position: PixelPosition, color: Rgba) -> Option<Rgba> {
+        let (index, offset) = tile_index(position);
+        let tile = self.tiles.entry(index).or_default();
+        let prev = std::mem::replace(&mut tile.colors[offset], color);
+        if prev.a != 0 {
+            return Some(prev);
+        }
+        tile.count += 1;
+        self.len += 1;
+        None
+    }
+
+    fn remove(&mut self, position: PixelPosition) -> Option<Rgba> {
+        let (index, offset) = tile_index(position);
+        let tile = self.tiles.get_mut(&index)?;
+        let prev = std::mem::replace(&mut tile.colors[offset], Rgba::new(0, 0, 0, 0));
+        if prev.a == 0 {
+            return None;
+        }
+        tile.count -= 1;
+        self.len -= 1;
+        if tile.count == 0 {
+            self.tiles.remove(&index);
+        }
+        Some(prev)
     }
 
     fn draw_pixel(&mut self, pixel: Pixel) -> Result<()> {
         let prev = if pixel.color.a == 0 {
-            self.pixels.remove(&pixel.position)
+            self.remove(pixel.position)
         } else {
-            self.pixels.insert(pixel.position, pixel.color)
+            self.insert(pixel.position, pixel.color)
         };
         prev.is_none().or_fail()?;
 
@@ -401,7 +1076,7 @@ impl Pixels {
     }
 
     fn erase_pixel(&mut self, pixel: Pixel) -> Result<()> {
-        let prev = self.pixels.remove(&pixel.position);
+        let prev = self.remove(pixel.position);
         (prev == Some(pixel.color)).or_fail()?;
 
         if pixel.position.is_non_negative() {
@@ -451,10 +1126,14 @@ impl Pixels {
 
 impl Serialize for Pixels {
     fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
-        self.pixels.len().serialize(writer).or_fail()?;
-        serialize_positions(writer, || self.pixels.keys().copied()).or_fail()?;
+        // Keep the same (row-major) order as the original format to make the output stable
+        let mut pixels = self.iter().collect::<Vec<_>>();
+        pixels.sort_unstable_by_key(|(position, _)| *position);
 
-        for color in self.pixels.values() {
+        pixels.len().serialize(writer).or_fail()?;
+        serialize_positions(writer, || pixels.iter().map(|(position, _)| *position)).or_fail()?;
+
+        for (_, color) in &pixels {
             color.serialize(writer).or_fail()?;
         }
 
@@ -465,18 +1144,18 @@ impl Serialize for Pixels {
 impl Deserialize for Pixels {
     fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
         let n = usize::deserialize(reader).or_fail()?;
-        let pixels = deserialize_positions(reader, n)
-            .or_fail()?
-            .map(|pos| Ok((pos, Rgba::deserialize(reader).or_fail()?)))
-            .collect::<Result<BTreeMap<_, _>>>()?;
+        let positions = deserialize_positions(reader, n).or_fail()?;
 
         let mut this = Self::default();
-        for position in pixels.keys().copied() {
+        for position in positions {
+            let color = Rgba::deserialize(reader).or_fail()?;
+            if color.a == 0 || this.insert(position, color).is_some() {
+                continue;
+            }
             if position.is_non_negative() {
                 this.on_non_neg_pixel_drawn(position);
             }
         }
-        this.pixels = pixels;
 
         Ok(this)
     }
@@ -523,3 +1202,521 @@ fn deserialize_positions<R: Read>(
 
     Ok(positions.into_iter())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(x: i16, y: i16, r: u8) -> Pixel {
+        Pixel::new(PixelPosition::from_xy(x, y), Rgba::new(r, 0, 0, 255))
+    }
+
+    fn canvas_pixels(canvas: &PixelCanvasModel) -> Vec<(i16, i16, u8)> {
+        canvas
+            .raw_pixels()
+            .map(|(position, color)| (position.x, position.y, color.r))
+            .collect()
+    }
+
+    #[test]
+    fn coalesce_commands_works() -> Result<()> {
+        let mut config = ConfigModel::default();
+        let mut canvas = PixelCanvasModel::default();
+        canvas
+            .draw_pixels(&config, [pixel(0, 0, 1)].into_iter())
+            .or_fail()?;
+
+        let start = canvas.command_log_tail();
+        canvas
+            .draw_pixels(&config, [pixel(1, 0, 2), pixel(0, 0, 5)].into_iter())
+            .or_fail()?;
+        canvas
+            .draw_pixels(&config, [pixel(1, 0, 3), pixel(2, 0, 4)].into_iter())
+            .or_fail()?;
+        canvas
+            .erase_pixels(&config, [PixelPosition::from_xy(2, 0)].into_iter())
+            .or_fail()?;
+        canvas
+            .draw_pixels(&config, [pixel(0, 0, 1)].into_iter())
+            .or_fail()?;
+        let expected = canvas_pixels(&canvas);
+        assert_eq!(expected, [(0, 0, 1), (1, 0, 3)]);
+
+        canvas.coalesce_commands(start);
+        assert_eq!(canvas.command_log().len(), 2);
+        assert_eq!(canvas.command_log_tail(), 2);
+        assert_eq!(canvas.command_log()[1].erase, []);
+        assert_eq!(canvas.command_log()[1].draw, [pixel(1, 0, 3)]);
+        assert_eq!(canvas_pixels(&canvas), expected);
+
+        // A single undo has the same effect as undoing all of the coalesced commands
+        canvas.undo_command(&mut config).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1)]);
+        canvas.redo_command(&mut config).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn enforce_command_log_budget_works() -> Result<()> {
+        let mut config = ConfigModel::default();
+        let mut canvas = PixelCanvasModel::default();
+        for x in 0..4 {
+            canvas
+                .draw_pixels(&config, [pixel(x, 0, 1)].into_iter())
+                .or_fail()?;
+        }
+        canvas.undo_command(&mut config).or_fail()?;
+        canvas
+            .draw_pixels(&config, [pixel(9, 0, 1)].into_iter())
+            .or_fail()?;
+        let logged_xs = |canvas: &PixelCanvasModel| {
+            canvas
+                .command_log()
+                .iter()
+                .map(|c| c.draw[0].position.x)
+                .collect::<Vec<_>>()
+        };
+        let size = canvas.command_log()[0].memory_usage();
+        assert_eq!(canvas.command_log_memory_usage(), size * 5);
+
+        // Unreachable branches are dropped first
+        canvas.enforce_command_log_budget(10, size * 4);
+        assert_eq!(canvas.branch_count(3), 0);
+        assert_eq!(logged_xs(&canvas), [0, 1, 2, 9]);
+
+        // Then the oldest commands
+        canvas.enforce_command_log_budget(10, size * 2);
+        assert_eq!(logged_xs(&canvas), [2, 9]);
+        assert_eq!(canvas.command_log_tail(), 2);
+        canvas.enforce_command_log_budget(1, usize::MAX);
+        assert_eq!(logged_xs(&canvas), [9]);
+        assert_eq!(canvas.command_log_tail(), 1);
+
+        // The current pixels are not affected
+        assert_eq!(canvas_pixels(&canvas).len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn command_tools_are_stored_in_trailer() -> Result<()> {
+        let config = ConfigModel::default();
+        let mut canvas = PixelCanvasModel::default();
+        canvas
+            .draw_pixels(&config, [pixel(0, 0, 1)].into_iter())
+            .or_fail()?;
+        canvas.set_last_command_tool(ToolKind::Fill);
+        canvas
+            .draw_pixels(&config, [pixel(1, 0, 2)].into_iter())
+            .or_fail()?;
+
+        let mut buf = Vec::new();
+        canvas.serialize_with_command_log(&mut buf, 10).or_fail()?;
+        let loaded = PixelCanvasModel::deserialize(&mut buf.as_slice()).or_fail()?;
+        let tools = loaded
+            .command_log
+            .iter()
+            .map(|c| c.tool)
+            .collect::<Vec<_>>();
+        assert_eq!(tools, [Some(ToolKind::Fill), None]);
+        assert_eq!(loaded.command_log_tail, 2);
+
+        // The same layout as the one read by older versions
+        let mut reader = libflate::deflate::Decoder::new(buf.as_slice());
+        let log: VecDeque<PixelCanvasCommand> = Deserialize::deserialize(&mut reader).or_fail()?;
+        let tail = usize::deserialize(&mut reader).or_fail()?;
+        let pixels = Pixels::deserialize(&mut reader).or_fail()?;
+        assert_eq!((log.len(), tail), (2, 2));
+        assert_eq!(pixels.iter().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_or_unknown_command_tools() -> Result<()> {
+        let encode = |trailer: &[u8]| -> Result<Vec<u8>> {
+            let mut writer = libflate::deflate::Encoder::new(Vec::new());
+            let mut command = PixelCanvasCommand::new();
+            command.draw.push(pixel(0, 0, 1));
+            1usize.serialize(&mut writer).or_fail()?;
+            command.serialize(&mut writer).or_fail()?;
+            1usize.serialize(&mut writer).or_fail()?;
+            Pixels::default().serialize(&mut writer).or_fail()?;
+            writer.write_all(trailer).or_fail()?;
+            writer.finish().into_result().or_fail()
+        };
+
+        for trailer in [&[][..], &[0, 0, 0, 1, 200][..]] {
+            let buf = encode(trailer).or_fail()?;
+            let loaded = PixelCanvasModel::deserialize(&mut buf.as_slice()).or_fail()?;
+            assert_eq!(loaded.command_log.len(), 1);
+            assert_eq!(loaded.command_log[0].tool, None);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn undo_tree_works() -> Result<()> {
+        let mut config = ConfigModel::default();
+        let mut canvas = PixelCanvasModel::default();
+        canvas
+            .draw_pixels(&config, [pixel(0, 0, 1)].into_iter())
+            .or_fail()?;
+        canvas
+            .draw_pixels(&config, [pixel(1, 0, 2)].into_iter())
+            .or_fail()?;
+
+        // Drawing after an undo keeps the undone command as a branch
+        canvas.undo_command(&mut config).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1)]);
+        canvas
+            .draw_pixels(&config, [pixel(2, 0, 3)].into_iter())
+            .or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1), (2, 0, 3)]);
+        assert_eq!(canvas.command_log_tail(), 2);
+        assert_eq!(canvas.command_log().len(), 2);
+        assert_eq!(canvas.branch_count(1), 1);
+
+        // Switching moves to the fork point and makes the branch redoable
+        canvas.switch_branch(&mut config, 1).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1)]);
+        assert_eq!(canvas.command_log_tail(), 1);
+        assert_eq!(canvas.branch_count(1), 1);
+        canvas.redo_command(&mut config).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1), (1, 0, 2)]);
+        assert_eq!(canvas.command_log_tail(), 2);
+        assert_eq!(canvas.command_log()[1].draw, [pixel(1, 0, 2)]);
+
+        // The previous line of history is still reachable
+        canvas.switch_branch(&mut config, 1).or_fail()?;
+        canvas.redo_command(&mut config).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), [(0, 0, 1), (2, 0, 3)]);
+        assert_eq!(canvas.command_log()[1].draw, [pixel(2, 0, 3)]);
+
+        canvas.jump_to_command(&mut config, 0).or_fail()?;
+        assert_eq!(canvas_pixels(&canvas), []);
+        assert_eq!(canvas.command_log_tail(), 0);
+        assert_eq!(canvas.branch_count(1), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn pixels_deserialize_baseline_format() -> Result<()> {
+        // Bytes written by the `BTreeMap` based implementation before pixels were stored in tiles
+        let bytes = [
+            0, 0, 0, 3, // count
+            0xff, 0xff, 0, 0, 0, 41, // y deltas
+            0xff, 0xff, 0, 6, 0xff, 0xfb, // x deltas
+            1, 0, 0, 255, 2, 0, 0, 255, 3, 0, 0, 128, // colors
+        ];
+        let pixels = Pixels::deserialize(&mut bytes.as_slice()).or_fail()?;
+        assert_eq!(
+            pixels.iter().collect::<Vec<_>>(),
+            [
+                (PixelPosition::from_xy(-1, -1), Rgba::new(1, 0, 0, 255)),
+                (PixelPosition::from_xy(5, -1), Rgba::new(2, 0, 0, 255)),
+                (PixelPosition::from_xy(0, 40), Rgba::new(3, 0, 0, 128)),
+            ]
+        );
+        assert_eq!(pixels.len, 3);
+        assert_eq!(pixels.non_neg_right_bottom, PixelPosition::from_xy(0, 40));
+
+        let mut buf = Vec::new();
+        pixels.serialize(&mut buf).or_fail()?;
+        assert_eq!(buf, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn pixels_tile_boundaries_work() -> Result<()> {
+        let mut config = ConfigModel::default();
+        config.frame.set_width(16);
+        config.frame.set_height(16);
+        let mut canvas = PixelCanvasModel::default();
+
+        let positions = [
+            (-33, 0),
+            (-32, -32),
+            (-1, -1),
+            (31, -1),
+            (31, 31),
+            (32, 31),
+            (0, 32),
+        ];
+        let pixels = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| pixel(x, y, i as u8 + 1));
+        canvas.draw_pixels(&config, pixels).or_fail()?;
+
+        for (i, &(x, y)) in positions.iter().enumerate() {
+            let position = PixelPosition::from_xy(x, y);
+            assert_eq!(
+                canvas.get_direct_pixel(position).map(|c| c.r),
+                Some(i as u8 + 1)
+            );
+        }
+        for (x, y) in [(-32, 0), (-33, -32), (0, 0), (0, -1), (32, 32), (-1, 31)] {
+            let position = PixelPosition::from_xy(x, y);
+            assert_eq!(canvas.get_direct_pixel(position), None, "({x}, {y})");
+        }
+        assert_eq!(
+            tile_index(PixelPosition::from_xy(-1, -1)),
+            ((-1, -1), TILE_AREA - 1)
+        );
+        assert_eq!(tile_index(PixelPosition::from_xy(-32, -32)), ((-1, -1), 0));
+        assert_eq!(
+            tile_index(PixelPosition::from_xy(-33, 0)),
+            ((0, -2), TILE_SIZE as usize - 1)
+        );
+
+        assert_eq!(canvas.pixels.len, positions.len());
+        assert_eq!(canvas.pixels.tiles.len(), 6);
+        assert_eq!(
+            canvas.region(),
+            PixelRegion::new(
+                PixelPosition::from_xy(-33, -32),
+                PixelPosition::from_xy(33, 33)
+            )
+        );
+
+        // Only non-negative pixels count for the frame (column) and layer (row) counts
+        assert_eq!(canvas.get_frames(&config), 3);
+        assert_eq!(canvas.get_layers(&config), 3);
+        canvas
+            .erase_pixels(
+                &config,
+                [(32, 31), (0, 32)]
+                    .into_iter()
+                    .map(|(x, y)| PixelPosition::from_xy(x, y)),
+            )
+            .or_fail()?;
+        assert_eq!(canvas.get_frames(&config), 2);
+        assert_eq!(canvas.get_layers(&config), 2);
+        assert_eq!(
+            canvas.region(),
+            PixelRegion::new(
+                PixelPosition::from_xy(-33, -32),
+                PixelPosition::from_xy(32, 32)
+            )
+        );
+
+        // Empty tiles are released
+        canvas.jump_to_command(&mut config, 0).or_fail()?;
+        assert_eq!(canvas.pixels.len, 0);
+        assert!(canvas.pixels.tiles.is_empty());
+        assert_eq!(canvas.region(), PixelRegion::default());
+        Ok(())
+    }
+
+    fn frame_pixel(
+        canvas: &PixelCanvasModel,
+        config: &ConfigModel,
+        frame: i16,
+        layer: i16,
+    ) -> Option<u8> {
+        let size = config.frame.get_base_region().size();
+        let position = PixelPosition::from_xy(
+            frame * size.width as i16 + 1,
+            layer * size.height as i16 + 2,
+        );
+        canvas.get_direct_pixel(position).map(|c| c.r)
+    }
+
+    fn frames_canvas(config: &mut ConfigModel) -> Result<PixelCanvasModel> {
+        let size = config.frame.get_base_region().size();
+        let mut canvas = PixelCanvasModel::default();
+        let pixels = (0..3).flat_map(|frame| {
+            (0..2).map(move |layer| {
+                pixel(
+                    frame * size.width as i16 + 1,
+                    layer * size.height as i16 + 2,
+                    (frame * 10 + layer + 1) as u8,
+                )
+            })
+        });
+        canvas.draw_pixels(config, pixels).or_fail()?;
+        config.frame_durations.set_millis(0, 100);
+        config.frame_durations.set_millis(2, 300);
+        Ok(canvas)
+    }
+
+    #[test]
+    fn rearrange_frames_works() -> Result<()> {
+        let mut config = ConfigModel::default();
+        let mut canvas = frames_canvas(&mut config).or_fail()?;
+        let frames = |canvas: &PixelCanvasModel, config: &ConfigModel, n: i16| {
+            (0..n)
+                .map(|frame| {
+                    let pixels = (
+                        frame_pixel(canvas, config, frame, 0),
+                        frame_pixel(canvas, config, frame, 1),
+                    );
+                    (pixels, config.frame_durations.get_millis(frame as usize))
+                })
+                .collect::<Vec<_>>()
+        };
+        let original = frames(&canvas, &config, 4);
+
+        // Insert after 0, duplicate 2, delete 1 and move 0 to the right
+        let cases: [&[Option<usize>]; 4] = [
+            &[Some(0), None, Some(1), Some(2)],
+            &[Some(0), Some(1), Some(2), Some(2)],
+            &[Some(0), Some(2)],
+            &[Some(1), Some(0), Some(2)],
+        ];
+        let expected = [
+            [
+                ((Some(1), Some(2)), Some(100)),
+                ((None, None), None),
+                ((Some(11), Some(12)), None),
+                ((Some(21), Some(22)), Some(300)),
+            ],
+            [
+                ((Some(1), Some(2)), Some(100)),
+                ((Some(11), Some(12)), None),
+                ((Some(21), Some(22)), Some(300)),
+                ((Some(21), Some(22)), Some(300)),
+            ],
+            [
+                ((Some(1), Some(2)), Some(100)),
+                ((Some(21), Some(22)), Some(300)),
+                ((None, None), None),
+                ((None, None), None),
+            ],
+            [
+                ((Some(11), Some(12)), None),
+                ((Some(1), Some(2)), Some(100)),
+                ((Some(21), Some(22)), Some(300)),
+                ((None, None), None),
+            ],
+        ];
+        for (sources, expected) in cases.into_iter().zip(expected) {
+            canvas.rearrange_frames(&mut config, sources).or_fail()?;
+            assert_eq!(frames(&canvas, &config, 4), expected);
+            assert_eq!(canvas.get_frames(&config), sources.len() as u16);
+
+            // A single undo restores both the pixels and the frame delays
+            canvas.undo_command(&mut config).or_fail()?;
+            assert_eq!(frames(&canvas, &config, 4), original);
+            canvas.redo_command(&mut config).or_fail()?;
+            assert_eq!(frames(&canvas, &config, 4), expected);
+            canvas.undo_command(&mut config).or_fail()?;
+        }
+
+        // Layout changes are kept in the saved undo history
+        canvas.rearrange_frames(&mut config, cases[2]).or_fail()?;
+        let mut buf = Vec::new();
+        canvas.serialize_with_command_log(&mut buf, 10).or_fail()?;
+        let mut loaded = PixelCanvasModel::deserialize(&mut buf.as_slice()).or_fail()?;
+        loaded.undo_command(&mut config).or_fail()?;
+        assert_eq!(frames(&loaded, &config, 4), original);
+        Ok(())
+    }
+
+    #[test]
+    fn rearrange_layers_works() -> Result<()> {
+        let mut config = ConfigModel::default();
+        let size = config.frame.get_base_region().size();
+        config.layer.set_count(3);
+        for layer in 0..3 {
+            config.layer_settings.get_mut(layer).name = format!("L{layer}");
+        }
+        config.layer_settings.get_mut(2).hidden = true;
+
+        let mut canvas = PixelCanvasModel::default();
+        let pixels = (0..2).flat_map(|frame| {
+            (0..3).map(move |layer| {
+                pixel(
+                    frame * size.width as i16 + 1,
+                    layer * size.height as i16 + 2,
+                    (frame * 10 + layer + 1) as u8,
+                )
+            })
+        });
+        canvas.draw_pixels(&config, pixels).or_fail()?;
+
+        let layers = |canvas: &PixelCanvasModel, config: &ConfigModel| {
+            let pixels = (0..2)
+                .map(|frame| {
+                    (0..4)
+                        .map(|layer| frame_pixel(canvas, config, frame, layer))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+            let settings = (0..config.layer.count() as usize)
+                .map(|layer| {
+                    let setting = config.layer_settings.get(layer);
+                    (setting.name.clone(), setting.hidden)
+                })
+                .collect::<Vec<_>>();
+            (pixels, settings)
+        };
+        let original = layers(&canvas, &config);
+        let setting = |name: &str, hidden| (name.to_owned(), hidden);
+
+        // Insert above 1, duplicate 1, delete 1, move 1 up and merge 2 down
+        let cases: [&[Vec<usize>]; 5] = [
+            &[vec![0], vec![1], vec![], vec![2]],
+            &[vec![0], vec![1], vec![1], vec![2]],
+            &[vec![0], vec![2]],
+            &[vec![0], vec![2], vec![1]],
+            &[vec![0], vec![1, 2]],
+        ];
+        let expected = [
+            (
+                [[1, 2, 0, 3], [11, 12, 0, 13]],
+                vec![
+                    setting("L0", false),
+                    setting("L1", false),
+                    setting("", false),
+                    setting("L2", true),
+                ],
+            ),
+            (
+                [[1, 2, 2, 3], [11, 12, 12, 13]],
+                vec![
+                    setting("L0", false),
+                    setting("L1", false),
+                    setting("L1", false),
+                    setting("L2", true),
+                ],
+            ),
+            (
+                [[1, 3, 0, 0], [11, 13, 0, 0]],
+                vec![setting("L0", false), setting("L2", true)],
+            ),
+            (
+                [[1, 3, 2, 0], [11, 13, 12, 0]],
+                vec![
+                    setting("L0", false),
+                    setting("L2", true),
+                    setting("L1", false),
+                ],
+            ),
+            // The hidden layer is merged as if it were visible
+            (
+                [[1, 3, 0, 0], [11, 13, 0, 0]],
+                vec![setting("L0", false), setting("L1", false)],
+            ),
+        ];
+        for (sources, (pixels, settings)) in cases.into_iter().zip(expected) {
+            let pixels = pixels
+                .iter()
+                .map(|frame| frame.iter().map(|&r| (r != 0).then_some(r)).collect())
+                .collect::<Vec<Vec<_>>>();
+            let expected = (pixels, settings);
+
+            canvas.rearrange_layers(&mut config, sources).or_fail()?;
+            assert_eq!(layers(&canvas, &config), expected);
+            assert_eq!(config.layer.count(), sources.len() as u16);
+
+            // A single undo restores the pixels, the layer settings and the layer count
+            canvas.undo_command(&mut config).or_fail()?;
+            assert_eq!(layers(&canvas, &config), original);
+            canvas.redo_command(&mut config).or_fail()?;
+            assert_eq!(layers(&canvas, &config), expected);
+            canvas.undo_command(&mut config).or_fail()?;
+        }
+        Ok(())
+    }
+}
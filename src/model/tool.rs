@@ -1,12 +1,22 @@
-use crate::{asset::IconId, marker::MarkerKind};
+use crate::{
+    asset::IconId,
+    marker::MarkerKind,
+    pixel::{Pixel, PixelPosition, PixelRegion},
+    serialize::{Deserialize, Serialize},
+};
+use orfail::OrFail;
 use pagurus::Result;
 use pagurus::image::Rgba;
+use std::io::{Read, Write};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ToolModel {
     pub current: ToolKind,
     pub draw: DrawTool,
     pub fill: FillToolState,
+    pub gradient: GradientToolState,
+    pub brush: BrushState,
+    pub symmetry: SymmetryState,
     pub erase: EraseTool,
     pub select: SelectTool,
     pub r#move: MoveToolState,
@@ -26,6 +36,7 @@ impl ToolModel {
             ToolKind::Move => self.r#move.marker,
             ToolKind::Pick => self.pick.marker,
             ToolKind::Fill => self.fill.marker,
+            ToolKind::Gradient => MarkerKind::Gradient,
         }
     }
 }
@@ -34,8 +45,8 @@ impl Default for ToolModel {
     fn default() -> Self {
         Self {
             current: ToolKind::Draw,
-            draw: DrawTool,
-            erase: EraseTool,
+            draw: DrawTool::default(),
+            erase: EraseTool::default(),
             select: SelectTool,
             r#move: MoveToolState {
                 marker: MarkerKind::Noop,
@@ -46,7 +57,22 @@ impl Default for ToolModel {
             },
             fill: FillToolState {
                 marker: MarkerKind::Fill,
+                tolerance: 0,
+                global: false,
+                diagonal: false,
+                bound: FillBound::None,
             },
+            gradient: GradientToolState {
+                pattern: DitherPattern::default(),
+                color: Rgba::new(255, 255, 255, 255),
+                custom: Vec::new(),
+            },
+            brush: BrushState {
+                kind: BrushKind::default(),
+                diameter: 3,
+                custom: Vec::new(),
+            },
+            symmetry: SymmetryState::default(),
         }
     }
 }
@@ -60,6 +86,7 @@ pub enum ToolKind {
     Select,
     Move,
     Pick,
+    Gradient,
 }
 
 impl ToolKind {
@@ -79,30 +106,119 @@ impl ToolKind {
             IconId::SelectBucket => Ok(Self::Select),
             IconId::Pick => Ok(Self::Pick),
             IconId::Move => Ok(Self::Move),
+            IconId::Gradient => Ok(Self::Gradient),
             _ => Err(orfail::Failure::new("unreachable")),
         }
     }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::Draw => IconId::PenStroke,
+            Self::Fill => IconId::Bucket,
+            Self::Erase => IconId::Erase,
+            Self::Select => IconId::Lasso,
+            Self::Move => IconId::Move,
+            Self::Pick => IconId::Pick,
+            Self::Gradient => IconId::Gradient,
+        }
+    }
+}
+
+impl Serialize for ToolKind {
+    fn serialize<W: Write>(&self, writer: &mut W) -> Result<()> {
+        (*self as u8).serialize(writer).or_fail()
+    }
+}
+
+impl Deserialize for ToolKind {
+    fn deserialize<R: Read>(reader: &mut R) -> Result<Self> {
+        match u8::deserialize(reader).or_fail()? {
+            0 => Ok(Self::Draw),
+            1 => Ok(Self::Fill),
+            2 => Ok(Self::Erase),
+            3 => Ok(Self::Select),
+            4 => Ok(Self::Move),
+            5 => Ok(Self::Pick),
+            6 => Ok(Self::Gradient),
+            n => Err(orfail::Failure::new(format!("unknown tool kind: {n}"))),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct DrawTool;
+pub enum Shape {
+    #[default]
+    Stroke,
+    Line,
+    Rectangle,
+    FilledRectangle,
+    Ellipse,
+    FilledEllipse,
+}
+
+impl Shape {
+    pub const ALL: [Self; 6] = [
+        Self::Stroke,
+        Self::Line,
+        Self::Rectangle,
+        Self::FilledRectangle,
+        Self::Ellipse,
+        Self::FilledEllipse,
+    ];
+
+    pub fn from_icon(icon: IconId) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|shape| shape.icon() == icon)
+            .ok_or_else(|| orfail::Failure::new("unreachable"))
+    }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::Stroke => IconId::PenStroke,
+            Self::Line => IconId::PenLine,
+            Self::Rectangle => IconId::PenRectangle,
+            Self::FilledRectangle => IconId::PenFilledRectangle,
+            Self::Ellipse => IconId::PenCircle,
+            Self::FilledEllipse => IconId::PenFilledCircle,
+        }
+    }
+
+    fn marker(self) -> MarkerKind {
+        match self {
+            Self::Stroke => MarkerKind::Stroke,
+            Self::Line => MarkerKind::Line,
+            Self::Rectangle => MarkerKind::Rectangle,
+            Self::FilledRectangle => MarkerKind::FilledRectangle,
+            Self::Ellipse => MarkerKind::Ellipse,
+            Self::FilledEllipse => MarkerKind::FilledEllipse,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DrawTool {
+    pub shape: Shape,
+}
 
 impl DrawTool {
     fn marker(self) -> MarkerKind {
-        MarkerKind::Stroke
+        self.shape.marker()
     }
 
     pub fn icon(self) -> IconId {
-        IconId::PenStroke
+        self.shape.icon()
     }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct EraseTool;
+pub struct EraseTool {
+    pub shape: Shape,
+}
 
 impl EraseTool {
     fn marker(self) -> MarkerKind {
-        MarkerKind::Stroke
+        self.shape.marker()
     }
 
     pub fn icon(self) -> IconId {
@@ -137,4 +253,332 @@ pub struct PickToolState {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct FillToolState {
     pub marker: MarkerKind,
+
+    // Maximum per-channel (RGBA) difference from the starting pixel color
+    pub tolerance: u8,
+
+    // Fill all pixels having the starting pixel color regardless of the connectivity
+    pub global: bool,
+
+    // Use 8-way connectivity instead of 4-way
+    pub diagonal: bool,
+
+    pub bound: FillBound,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FillBound {
+    #[default]
+    None,
+    Frame,
+    Selection,
+}
+
+impl FillBound {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Frame,
+            Self::Frame => Self::Selection,
+            Self::Selection => Self::None,
+        }
+    }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::None => IconId::FillBoundNone,
+            Self::Frame => IconId::FillBoundFrame,
+            Self::Selection => IconId::FillBoundSelection,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradientToolState {
+    pub pattern: DitherPattern,
+
+    // The end color of the gradient (the start color is the drawing color)
+    pub color: Rgba,
+
+    // Gray levels (rows) captured from the selection for `DitherPattern::Custom`
+    pub custom: Vec<Vec<u8>>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DitherPattern {
+    Bayer2,
+    #[default]
+    Bayer4,
+    Bayer8,
+    Checker,
+    Custom,
+}
+
+impl DitherPattern {
+    pub const ALL: [Self; 5] = [
+        Self::Bayer2,
+        Self::Bayer4,
+        Self::Bayer8,
+        Self::Checker,
+        Self::Custom,
+    ];
+
+    pub fn from_icon(icon: IconId) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|pattern| pattern.icon() == icon)
+            .ok_or_else(|| orfail::Failure::new("unreachable"))
+    }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::Bayer2 => IconId::DitherBayer2,
+            Self::Bayer4 => IconId::DitherBayer4,
+            Self::Bayer8 => IconId::DitherBayer8,
+            Self::Checker => IconId::DitherChecker,
+            Self::Custom => IconId::DitherCustom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrushState {
+    pub kind: BrushKind,
+
+    // The diameter of `BrushKind::Round`
+    pub diameter: u8,
+
+    // Pixels captured from the selection for `BrushKind::Custom` (relative to the brush center)
+    pub custom: Vec<Pixel>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BrushKind {
+    // Rectangular blocks aligned to the minimum pixel size
+    #[default]
+    Square,
+    Round,
+    Custom,
+}
+
+impl BrushKind {
+    pub const ALL: [Self; 3] = [Self::Square, Self::Round, Self::Custom];
+
+    pub fn from_icon(icon: IconId) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.icon() == icon)
+            .ok_or_else(|| orfail::Failure::new("unreachable"))
+    }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::Square => IconId::BrushSquare,
+            Self::Round => IconId::BrushRound,
+            Self::Custom => IconId::BrushCustom,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SymmetryState {
+    pub mode: SymmetryMode,
+
+    // Doubled offsets of the mirror axes from the top-left corner of a frame (`None` means the frame center)
+    pub axis: Option<PixelPosition>,
+}
+
+impl SymmetryState {
+    // Returns `position` and its mirrored positions in the frame (of the animation frame and layer) containing it
+    pub fn mirror(
+        self,
+        frame: PixelRegion,
+        position: PixelPosition,
+    ) -> impl Iterator<Item = PixelPosition> {
+        let axis = self.frame_axis(frame, position);
+        let x = (i32::from(axis.x) - 1 - i32::from(position.x)) as i16;
+        let y = (i32::from(axis.y) - 1 - i32::from(position.y)) as i16;
+        let mirrored = match self.mode {
+            SymmetryMode::None => [None, None, None],
+            SymmetryMode::Horizontal => [Some(PixelPosition::from_xy(x, position.y)), None, None],
+            SymmetryMode::Vertical => [Some(PixelPosition::from_xy(position.x, y)), None, None],
+            SymmetryMode::FourWay => [
+                Some(PixelPosition::from_xy(x, position.y)),
+                Some(PixelPosition::from_xy(position.x, y)),
+                Some(PixelPosition::from_xy(x, y)),
+            ],
+        };
+        std::iter::once(position).chain(mirrored.into_iter().flatten())
+    }
+
+    // Doubled absolute position of the mirror axes of the frame containing `position`
+    pub fn frame_axis(self, frame: PixelRegion, position: PixelPosition) -> PixelPosition {
+        let size = frame.size();
+        let origin = Self::frame_origin(frame, position);
+        let axis = self.axis.unwrap_or(PixelPosition::from_xy(
+            size.width as i16,
+            size.height as i16,
+        ));
+        PixelPosition::from_xy(
+            (i32::from(origin.x) * 2 + i32::from(axis.x)) as i16,
+            (i32::from(origin.y) * 2 + i32::from(axis.y)) as i16,
+        )
+    }
+
+    // Doubled offsets of the center of `region` from the top-left corner of the frame containing it
+    pub fn region_axis(frame: PixelRegion, region: PixelRegion) -> PixelPosition {
+        let origin = Self::frame_origin(frame, region.center());
+        PixelPosition::from_xy(
+            region.start.x + region.end.x - origin.x * 2,
+            region.start.y + region.end.y - origin.y * 2,
+        )
+    }
+
+    // Top-left corner of the frame (of the animation frame and layer) containing `position`
+    pub fn frame_origin(frame: PixelRegion, position: PixelPosition) -> PixelPosition {
+        let size = frame.size();
+        let w = size.width.max(1) as i16;
+        let h = size.height.max(1) as i16;
+        PixelPosition::from_xy(
+            frame.start.x + (position.x - frame.start.x).div_euclid(w) * w,
+            frame.start.y + (position.y - frame.start.y).div_euclid(h) * h,
+        )
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SymmetryMode {
+    #[default]
+    None,
+
+    // Mirrors left and right (the axis is vertical)
+    Horizontal,
+
+    // Mirrors top and bottom (the axis is horizontal)
+    Vertical,
+
+    FourWay,
+}
+
+impl SymmetryMode {
+    pub fn next(self) -> Self {
+        match self {
+            Self::None => Self::Horizontal,
+            Self::Horizontal => Self::Vertical,
+            Self::Vertical => Self::FourWay,
+            Self::FourWay => Self::None,
+        }
+    }
+
+    pub fn icon(self) -> IconId {
+        match self {
+            Self::None => IconId::SymmetryNone,
+            Self::Horizontal => IconId::SymmetryHorizontal,
+            Self::Vertical => IconId::SymmetryVertical,
+            Self::FourWay => IconId::SymmetryFourWay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mirror(symmetry: SymmetryState, frame: PixelRegion, x: i16, y: i16) -> Vec<(i16, i16)> {
+        symmetry
+            .mirror(frame, PixelPosition::from_xy(x, y))
+            .map(|p| (p.x, p.y))
+            .collect()
+    }
+
+    fn frame(width: i16, height: i16) -> PixelRegion {
+        PixelRegion::new(
+            PixelPosition::from_xy(0, 0),
+            PixelPosition::from_xy(width, height),
+        )
+    }
+
+    #[test]
+    fn mirror_even_and_odd_axes_works() {
+        let horizontal = SymmetryState {
+            mode: SymmetryMode::Horizontal,
+            axis: None,
+        };
+        let vertical = SymmetryState {
+            mode: SymmetryMode::Vertical,
+            axis: None,
+        };
+
+        assert_eq!(mirror(horizontal, frame(8, 8), 1, 2), [(1, 2), (6, 2)]);
+        assert_eq!(mirror(horizontal, frame(8, 8), 4, 2), [(4, 2), (3, 2)]);
+        assert_eq!(mirror(vertical, frame(8, 8), 1, 2), [(1, 2), (1, 5)]);
+
+        // The center column or row of an odd sized frame is mirrored onto itself
+        assert_eq!(mirror(horizontal, frame(7, 7), 1, 2), [(1, 2), (5, 2)]);
+        assert_eq!(mirror(horizontal, frame(7, 7), 3, 2), [(3, 2), (3, 2)]);
+        assert_eq!(mirror(vertical, frame(7, 7), 1, 3), [(1, 3), (1, 3)]);
+
+        let none = SymmetryState::default();
+        assert_eq!(mirror(none, frame(8, 8), 1, 2), [(1, 2)]);
+    }
+
+    #[test]
+    fn mirror_with_selection_axis_works() {
+        let frame = frame(8, 8);
+        let selection = |x0, x1| {
+            PixelRegion::from_positions(
+                [(x0, 1), (x1, 1), (x0, 2)]
+                    .into_iter()
+                    .map(|(x, y)| PixelPosition::from_xy(x, y)),
+            )
+        };
+
+        // A selection in the second frame has the same axis offset as the first one
+        let axis = SymmetryState::region_axis(frame, selection(2, 4));
+        assert_eq!(axis, PixelPosition::from_xy(7, 4));
+        assert_eq!(SymmetryState::region_axis(frame, selection(10, 12)), axis);
+
+        let symmetry = SymmetryState {
+            mode: SymmetryMode::Horizontal,
+            axis: Some(axis),
+        };
+        assert_eq!(mirror(symmetry, frame, 2, 5), [(2, 5), (4, 5)]);
+        assert_eq!(mirror(symmetry, frame, 3, 5), [(3, 5), (3, 5)]);
+        assert_eq!(mirror(symmetry, frame, 17, 5), [(17, 5), (21, 5)]);
+
+        let axis = SymmetryState::region_axis(frame, selection(2, 5));
+        assert_eq!(axis, PixelPosition::from_xy(8, 4));
+        let symmetry = SymmetryState {
+            mode: SymmetryMode::Vertical,
+            axis: Some(axis),
+        };
+        assert_eq!(mirror(symmetry, frame, 3, 0), [(3, 0), (3, 3)]);
+    }
+
+    #[test]
+    fn mirror_four_way_in_other_frame_works() {
+        let frame = frame(8, 8);
+        let symmetry = SymmetryState {
+            mode: SymmetryMode::FourWay,
+            axis: None,
+        };
+        let position = PixelPosition::from_xy(9, 17);
+        assert_eq!(
+            SymmetryState::frame_origin(frame, position),
+            PixelPosition::from_xy(8, 16)
+        );
+        assert_eq!(
+            symmetry.frame_axis(frame, position),
+            PixelPosition::from_xy(24, 40)
+        );
+        assert_eq!(
+            mirror(symmetry, frame, 9, 17),
+            [(9, 17), (14, 17), (9, 22), (14, 22)]
+        );
+
+        // Positions in front of the base frame are mirrored in their own frame
+        assert_eq!(
+            mirror(symmetry, frame, -1, -8),
+            [(-1, -8), (-8, -8), (-1, -1), (-8, -1)]
+        );
+    }
 }
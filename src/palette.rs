@@ -0,0 +1,219 @@
+use orfail::{Failure, OrFail, Result};
+use pagurus::image::Rgba;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteFormat {
+    // GIMP palette (.gpl)
+    Gpl,
+    // JASC-PAL (.pal)
+    JascPal,
+    // Paint.NET palette (.txt)
+    PaintNet,
+    // Plain list of `RRGGBB` hex codes (.hex)
+    Hex,
+}
+
+impl PaletteFormat {
+    pub fn from_extension(extension: &str) -> Result<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "gpl" => Ok(Self::Gpl),
+            "pal" => Ok(Self::JascPal),
+            "txt" => Ok(Self::PaintNet),
+            "hex" => Ok(Self::Hex),
+            _ => Err(Failure::new(format!(
+                "unsupported palette file extension: {extension:?}"
+            ))),
+        }
+    }
+}
+
+pub fn parse_palette(text: &str) -> Result<Vec<Rgba>> {
+    let text = text.trim_start_matches('\u{feff}');
+    match text.lines().next().map(|line| line.trim()) {
+        Some("GIMP Palette") => parse_gpl(text).or_fail(),
+        Some("JASC-PAL") => parse_jasc_pal(text).or_fail(),
+        _ => parse_hex_list(text).or_fail(),
+    }
+}
+
+pub fn format_palette(colors: &[Rgba], format: PaletteFormat) -> String {
+    let mut text = String::new();
+    match format {
+        PaletteFormat::Gpl => {
+            let _ = writeln!(text, "GIMP Palette");
+            let _ = writeln!(text, "Name: Pixcil");
+            let _ = writeln!(text, "#");
+            for c in colors {
+                let _ = writeln!(
+                    text,
+                    "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}",
+                    c.r, c.g, c.b, c.r, c.g, c.b
+                );
+            }
+        }
+        PaletteFormat::JascPal => {
+            let _ = write!(text, "JASC-PAL\r\n0100\r\n{}\r\n", colors.len());
+            for c in colors {
+                let _ = write!(text, "{} {} {}\r\n", c.r, c.g, c.b);
+            }
+        }
+        PaletteFormat::PaintNet => {
+            let _ = writeln!(text, "; paint.net Palette File");
+            let _ = writeln!(text, "; Colors: {}", colors.len());
+            for c in colors {
+                let _ = writeln!(text, "{:02X}{:02X}{:02X}{:02X}", c.a, c.r, c.g, c.b);
+            }
+        }
+        PaletteFormat::Hex => {
+            for c in colors {
+                let _ = writeln!(text, "{:02x}{:02x}{:02x}", c.r, c.g, c.b);
+            }
+        }
+    }
+    text
+}
+
+fn parse_gpl(text: &str) -> Result<Vec<Rgba>> {
+    let mut colors = Vec::new();
+    for line in text.lines().skip(1).map(|line| line.trim()) {
+        if line.is_empty()
+            || line.starts_with('#')
+            || line.starts_with("Name:")
+            || line.starts_with("Columns:")
+        {
+            continue;
+        }
+        let mut tokens = line.split_whitespace();
+        colors.push(parse_rgb(&mut tokens).or_fail_with(|e| format!("{e}: {line:?}"))?);
+    }
+    Ok(colors)
+}
+
+fn parse_jasc_pal(text: &str) -> Result<Vec<Rgba>> {
+    let mut lines = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty());
+    (lines.next() == Some("JASC-PAL")).or_fail()?;
+    lines
+        .next()
+        .or_fail_with(|_| "missing JASC-PAL version".to_owned())?;
+    let count = lines
+        .next()
+        .and_then(|line| line.parse::<usize>().ok())
+        .or_fail_with(|_| "missing or invalid JASC-PAL color count".to_owned())?;
+
+    let mut colors = Vec::with_capacity(count);
+    for line in lines.take(count) {
+        let mut tokens = line.split_whitespace();
+        colors.push(parse_rgb(&mut tokens).or_fail_with(|e| format!("{e}: {line:?}"))?);
+    }
+    (colors.len() == count).or_fail_with(|_| {
+        format!(
+            "JASC-PAL color count mismatch: expected {count}, got {}",
+            colors.len()
+        )
+    })?;
+    Ok(colors)
+}
+
+fn parse_hex_list(text: &str) -> Result<Vec<Rgba>> {
+    let mut colors = Vec::new();
+    for line in text.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let hex = line.trim_start_matches('#');
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6 || hex.len() == 8)
+            .or_fail_with(|_| format!("invalid hex color: {line:?}"))?;
+        let [a, r, g, b] = value.to_be_bytes();
+        let a = if hex.len() == 6 { u8::MAX } else { a };
+        colors.push(Rgba::new(r, g, b, a));
+    }
+    Ok(colors)
+}
+
+fn parse_rgb<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<Rgba> {
+    let mut next = || {
+        tokens
+            .next()
+            .and_then(|t| t.parse::<u8>().ok())
+            .or_fail_with(|_| "invalid RGB color".to_owned())
+    };
+    Ok(Rgba::new(next()?, next()?, next()?, u8::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLORS: [Rgba; 3] = [
+        Rgba::new(0, 0, 0, 255),
+        Rgba::new(255, 128, 1, 255),
+        Rgba::new(18, 52, 86, 255),
+    ];
+
+    #[test]
+    fn round_trip_works() -> Result<()> {
+        for format in [
+            PaletteFormat::Gpl,
+            PaletteFormat::JascPal,
+            PaletteFormat::PaintNet,
+            PaletteFormat::Hex,
+        ] {
+            let text = format_palette(&COLORS, format);
+            assert_eq!(parse_palette(&text).or_fail()?, COLORS, "{format:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn parse_gpl_works() -> Result<()> {
+        let text = "GIMP Palette\r\nName: Test\r\nColumns: 4\r\n# comment\r\n\r\n  0   0   0\tBlack\r\n255 128   1\r\n";
+        assert_eq!(parse_palette(text).or_fail()?, COLORS[..2]);
+
+        assert!(parse_palette("GIMP Palette\n0 0\n").is_err());
+        assert!(parse_palette("GIMP Palette\n0 0 256\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_jasc_pal_works() -> Result<()> {
+        let text = "\u{feff}JASC-PAL\r\n0100\r\n2\r\n0 0 0\r\n255 128 1\r\n";
+        assert_eq!(parse_palette(text).or_fail()?, COLORS[..2]);
+
+        assert!(parse_palette("JASC-PAL\n0100\n3\n0 0 0\n").is_err());
+        assert!(parse_palette("JASC-PAL\n0100\nfoo\n").is_err());
+        assert!(parse_palette("JASC-PAL\n0100\n1\n0 x 0\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_paint_net_works() -> Result<()> {
+        let text = "; paint.net Palette File\r\n; Colors: 2\r\nFF000000\r\n80FF8001\r\n";
+        assert_eq!(
+            parse_palette(text).or_fail()?,
+            [Rgba::new(0, 0, 0, 255), Rgba::new(255, 128, 1, 128)]
+        );
+
+        let colors = [Rgba::new(1, 2, 3, 0), Rgba::new(4, 5, 6, 127)];
+        let text = format_palette(&colors, PaletteFormat::PaintNet);
+        assert_eq!(parse_palette(&text).or_fail()?, colors);
+
+        assert!(parse_palette("; paint.net Palette File\nFF00000\n").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_hex_works() -> Result<()> {
+        let text = "000000\r\n#ff8001\r\n\r\n123456\r\n";
+        assert_eq!(parse_palette(text).or_fail()?, COLORS);
+
+        assert!(parse_palette("ff80\n").is_err());
+        assert!(parse_palette("gg8001\n").is_err());
+        Ok(())
+    }
+}
@@ -22,7 +22,7 @@ impl Pixel {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize)]
 pub struct PixelPosition {
     pub y: i16,
     pub x: i16,
@@ -128,7 +128,7 @@ impl Sub for PixelPosition {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct PixelRegion {
     pub start: PixelPosition,
     pub end: PixelPosition,
@@ -249,6 +249,14 @@ impl PixelRegion {
             && position.y < self.end.y
     }
 
+    pub fn wrap(self, position: PixelPosition) -> PixelPosition {
+        let size = self.size();
+        PixelPosition::from_xy(
+            self.start.x + (position.x - self.start.x).rem_euclid(size.width as i16),
+            self.start.y + (position.y - self.start.y).rem_euclid(size.height as i16),
+        )
+    }
+
     pub fn center(self) -> PixelPosition {
         let mut size = self.size();
         size.width /= 2;
@@ -301,7 +309,7 @@ impl Deserialize for PixelRegion {
     }
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct PixelSize {
     // TODO: Make private
     pub width: u16,
@@ -440,3 +448,105 @@ impl PixelLine {
         pixels.into_iter()
     }
 }
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PixelEllipse {
+    pub region: PixelRegion,
+}
+
+impl PixelEllipse {
+    pub const fn new(region: PixelRegion) -> Self {
+        Self { region }
+    }
+
+    pub fn contains(self, position: PixelPosition) -> bool {
+        if !self.region.contains(position) {
+            return false;
+        }
+
+        let size = self.region.size();
+        let rx = size.width as f64 / 2.0;
+        let ry = size.height as f64 / 2.0;
+        let dx = (position.x - self.region.start.x) as f64 + 0.5 - rx;
+        let dy = (position.y - self.region.start.y) as f64 + 0.5 - ry;
+        (dx * dx) / (rx * rx) + (dy * dy) / (ry * ry) <= 1.0
+    }
+
+    pub fn pixels(self) -> impl Iterator<Item = PixelPosition> {
+        self.region.pixels().filter(move |&p| self.contains(p))
+    }
+
+    pub fn edges(self) -> impl Iterator<Item = PixelPosition> {
+        self.pixels().filter(move |&p| {
+            !(self.contains(p.move_x(-1))
+                && self.contains(p.move_x(1))
+                && self.contains(p.move_y(-1))
+                && self.contains(p.move_y(1)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(region: PixelRegion, pixels: impl Iterator<Item = PixelPosition>) -> Vec<String> {
+        let pixels = pixels.collect::<Vec<_>>();
+        (region.start.y..region.end.y)
+            .map(|y| {
+                (region.start.x..region.end.x)
+                    .map(|x| {
+                        if pixels.contains(&PixelPosition::from_xy(x, y)) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ellipse_works() {
+        let region = PixelRegion::new(PixelPosition::from_xy(-2, -1), PixelPosition::from_xy(3, 2));
+        let ellipse = PixelEllipse::new(region);
+        assert_eq!(
+            render(region, ellipse.pixels()),
+            [".###.", "#####", ".###."]
+        );
+        assert_eq!(render(region, ellipse.edges()), [".###.", "#...#", ".###."]);
+        assert!(!ellipse.contains(PixelPosition::from_xy(3, 0)));
+
+        let region = PixelRegion::new(PixelPosition::from_xy(0, 0), PixelPosition::from_xy(7, 5));
+        let ellipse = PixelEllipse::new(region);
+        assert_eq!(
+            render(region, ellipse.pixels()),
+            [".#####.", "#######", "#######", "#######", ".#####."]
+        );
+        assert_eq!(
+            render(region, ellipse.edges()),
+            [".#####.", "#.....#", "#.....#", "#.....#", ".#####."]
+        );
+    }
+
+    #[test]
+    fn wrap_works() {
+        let region = PixelRegion::new(PixelPosition::from_xy(2, 3), PixelPosition::from_xy(6, 8));
+        let wrap = |x, y| {
+            let p = region.wrap(PixelPosition::from_xy(x, y));
+            (p.x, p.y)
+        };
+        assert_eq!(wrap(3, 4), (3, 4));
+        assert_eq!(wrap(6, 8), (2, 3));
+        assert_eq!(wrap(1, 2), (5, 7));
+
+        // Negative coordinates
+        assert_eq!(wrap(-1, -1), (3, 4));
+        assert_eq!(wrap(-10, -20), (2, 5));
+
+        // Several tiles away
+        assert_eq!(wrap(2 + 4 * 7 + 1, 3 + 5 * 9 + 4), (3, 7));
+        assert_eq!(wrap(2 - 4 * 7 + 3, 3 - 5 * 9), (5, 3));
+    }
+}
@@ -1,5 +1,10 @@
-use orfail::{OrFail, Result};
-use pagurus::{image::Sprite, spatial::Size};
+use crate::pixel::PixelSize;
+use orfail::{Failure, OrFail, Result};
+use pagurus::{
+    image::{Rgba, Sprite},
+    spatial::Size,
+};
+use std::collections::HashMap;
 
 pub fn decode_sprite(png: &[u8]) -> Result<Sprite> {
     let decoder = png::Decoder::new(png);
@@ -30,3 +35,382 @@ pub fn decode_sprite(png: &[u8]) -> Result<Sprite> {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct AnimationFrame {
+    pub image: Sprite,
+    pub delay_num: u16,
+    pub delay_den: u16,
+}
+
+pub fn decode_animation_frames(png: &[u8]) -> Result<Vec<AnimationFrame>> {
+    let mut decoder = png::Decoder::new(png);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().or_fail()?;
+
+    let width = reader.info().width as usize;
+    let height = reader.info().height as usize;
+    let size = Size::from_wh(width as u32, height as u32);
+    let mut frame_count = reader
+        .info()
+        .animation_control
+        .map_or(1, |a| a.num_frames as usize);
+    if reader.info().animation_control.is_some() && reader.info().frame_control.is_none() {
+        // The default image is not a part of the animation.
+        frame_count += 1;
+    }
+
+    let mut canvas = vec![Rgba::new(0, 0, 0, 0); width * height];
+    let mut frames = Vec::new();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    for i in 0..frame_count {
+        let info = reader.next_frame(&mut buf).or_fail()?;
+        let sub_image = to_rgba_pixels(info.color_type, &buf[..info.buffer_size()]).or_fail()?;
+
+        let Some(mut fctl) = reader.info().frame_control else {
+            if reader.info().animation_control.is_some() {
+                continue;
+            }
+            let image = Sprite::from_rgba32_bytes(&to_rgba_bytes(&sub_image), size).or_fail()?;
+            frames.push(AnimationFrame {
+                image,
+                delay_num: 0,
+                delay_den: 0,
+            });
+            break;
+        };
+        if i == 0 && fctl.dispose_op == png::DisposeOp::Previous {
+            fctl.dispose_op = png::DisposeOp::Background;
+        }
+
+        let x_offset = fctl.x_offset as usize;
+        let y_offset = fctl.y_offset as usize;
+        let sub_width = fctl.width as usize;
+        let sub_height = fctl.height as usize;
+        (x_offset + sub_width <= width && y_offset + sub_height <= height)
+            .or_fail_with(|_| "APNG frame is out of the image bounds".to_owned())?;
+
+        let previous = (fctl.dispose_op == png::DisposeOp::Previous).then(|| canvas.clone());
+        for y in 0..sub_height {
+            for x in 0..sub_width {
+                let src = sub_image[y * sub_width + x];
+                let dst = &mut canvas[(y + y_offset) * width + x + x_offset];
+                *dst = match fctl.blend_op {
+                    png::BlendOp::Source => src,
+                    png::BlendOp::Over if src.a == 0 => *dst,
+                    png::BlendOp::Over => src.alpha_blend(*dst),
+                };
+            }
+        }
+
+        let image = Sprite::from_rgba32_bytes(&to_rgba_bytes(&canvas), size).or_fail()?;
+        frames.push(AnimationFrame {
+            image,
+            delay_num: fctl.delay_num,
+            delay_den: if fctl.delay_den == 0 {
+                100
+            } else {
+                fctl.delay_den
+            },
+        });
+
+        match fctl.dispose_op {
+            png::DisposeOp::None => {}
+            png::DisposeOp::Background => {
+                for y in y_offset..y_offset + sub_height {
+                    canvas[y * width + x_offset..y * width + x_offset + sub_width]
+                        .fill(Rgba::new(0, 0, 0, 0));
+                }
+            }
+            png::DisposeOp::Previous => {
+                canvas = previous.or_fail()?;
+            }
+        }
+    }
+    Ok(frames)
+}
+
+fn to_rgba_pixels(color_type: png::ColorType, bytes: &[u8]) -> Result<Vec<Rgba>> {
+    let pixels = match color_type {
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .map(|p| Rgba::new(p[0], p[1], p[2], 255))
+            .collect(),
+        png::ColorType::Rgba => bytes
+            .chunks_exact(4)
+            .map(|p| Rgba::new(p[0], p[1], p[2], p[3]))
+            .collect(),
+        png::ColorType::Grayscale => bytes.iter().map(|&v| Rgba::new(v, v, v, 255)).collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .map(|p| Rgba::new(p[0], p[0], p[0], p[1]))
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(Failure::new("unexpected indexed PNG output"));
+        }
+    };
+    Ok(pixels)
+}
+
+fn to_rgba_bytes(pixels: &[Rgba]) -> Vec<u8> {
+    pixels.iter().flat_map(|p| [p.r, p.g, p.b, p.a]).collect()
+}
+
+pub fn encode_rgba(size: PixelSize, rgba_bytes: &[u8]) -> Result<Vec<u8>> {
+    let mut png_data = Vec::new();
+    {
+        let mut encoder =
+            png::Encoder::new(&mut png_data, u32::from(size.width), u32::from(size.height));
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Best);
+
+        let mut writer = encoder.write_header().or_fail()?;
+        writer.write_image_data(rgba_bytes).or_fail()?;
+    }
+    Ok(png_data)
+}
+
+#[derive(Debug)]
+pub struct IndexedImage {
+    pub depth: png::BitDepth,
+    pub palette: Vec<u8>,
+    pub trns: Vec<u8>,
+    pub frames: Vec<Vec<u8>>,
+}
+
+pub fn to_indexed(width: usize, frames: &[Vec<u8>]) -> Option<IndexedImage> {
+    let mut colors = Vec::<Rgba>::new();
+    let mut indices = HashMap::new();
+    for frame in frames {
+        for c in frame.chunks_exact(4) {
+            let color = Rgba::new(c[0], c[1], c[2], c[3]);
+            if indices.contains_key(&color) {
+                continue;
+            }
+            if colors.len() == 256 {
+                return None;
+            }
+            indices.insert(color, 0);
+            colors.push(color);
+        }
+    }
+
+    // Put translucent colors first to keep the tRNS chunk short.
+    colors.sort_by_key(|c| c.a == u8::MAX);
+    for (i, color) in colors.iter().enumerate() {
+        indices.insert(*color, i as u8);
+    }
+
+    let (depth, bits) = match colors.len() {
+        0..=2 => (png::BitDepth::One, 1),
+        3..=4 => (png::BitDepth::Two, 2),
+        5..=16 => (png::BitDepth::Four, 4),
+        _ => (png::BitDepth::Eight, 8),
+    };
+    let pixels_per_byte = 8 / bits;
+    let frames = frames
+        .iter()
+        .map(|frame| {
+            let mut data = Vec::new();
+            for row in frame.chunks(width * 4) {
+                for pixels in row.chunks(pixels_per_byte * 4) {
+                    let mut byte = 0;
+                    for (i, c) in pixels.chunks_exact(4).enumerate() {
+                        let index = indices[&Rgba::new(c[0], c[1], c[2], c[3])];
+                        byte |= index << (8 - bits * (i + 1));
+                    }
+                    data.push(byte);
+                }
+            }
+            data
+        })
+        .collect();
+
+    Some(IndexedImage {
+        depth,
+        palette: colors.iter().flat_map(|c| [c.r, c.g, c.b]).collect(),
+        trns: colors
+            .iter()
+            .take_while(|c| c.a != u8::MAX)
+            .map(|c| c.a)
+            .collect(),
+        frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        model::Models,
+        pixel::{Pixel, PixelPosition},
+    };
+
+    fn colors(frame: &AnimationFrame) -> Vec<Rgba> {
+        frame.image.pixels().map(|(_, c)| c).collect()
+    }
+
+    fn animation_models() -> Result<Models> {
+        let mut models = Models::default();
+        models.config.frame.set_width(4);
+        models.config.frame.set_height(2);
+        models.config.animation.set_enabled(true);
+        models.config.animation.set_frame_count(3);
+        models.config.frame_durations.set_millis(0, 100);
+        models.config.frame_durations.set_millis(1, 250);
+
+        let start = models.config.frame.get_base_region().start;
+        let pixels = (0..3).flat_map(|frame| {
+            [
+                (frame, 0, Rgba::new(255, frame as u8, 0, 255)),
+                (3 - frame, 1, Rgba::new(0, 0, 255, 128)),
+            ]
+            .map(|(x, y, color)| {
+                let position = PixelPosition::from_xy(start.x + frame * 4 + x, start.y + y);
+                Pixel::new(position, color)
+            })
+        });
+        models
+            .pixel_canvas
+            .draw_pixels(&models.config, pixels)
+            .or_fail()?;
+        Ok(models)
+    }
+
+    #[test]
+    fn apng_round_trip_works() -> Result<()> {
+        for indexed_png in [false, true] {
+            let mut models = animation_models().or_fail()?;
+            models.config.indexed_png = indexed_png;
+            let png_data = models.to_png().or_fail()?;
+
+            let frames = decode_animation_frames(&png_data).or_fail()?;
+            assert_eq!(frames.len(), 3);
+            let fps = u16::from(models.config.animation.fps());
+            let delays = [(100, 1000), (250, 1000), (1, fps)];
+            for (i, frame) in frames.iter().enumerate() {
+                let expected = models.frame_image_data(i as u16);
+                let actual = colors(frame)
+                    .into_iter()
+                    .flat_map(|c| [c.r, c.g, c.b, c.a])
+                    .collect::<Vec<_>>();
+                assert_eq!(actual, expected, "indexed_png={indexed_png}, frame={i}");
+                assert_eq!((frame.delay_num, frame.delay_den), delays[i]);
+            }
+
+            let loaded = Models::from_png(&png_data).or_fail()?;
+            for i in 0..3 {
+                assert_eq!(loaded.frame_image_data(i), models.frame_image_data(i));
+            }
+            assert_eq!(loaded.config.frame_durations, models.config.frame_durations);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn decode_apng_dispose_and_blend_works() -> Result<()> {
+        let red = Rgba::new(255, 0, 0, 255);
+        let blue = Rgba::new(0, 0, 255, 255);
+        let green = Rgba::new(0, 255, 0, 128);
+        let white = Rgba::new(255, 255, 255, 255);
+        let clear = Rgba::new(0, 0, 0, 0);
+
+        let mut png_data = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_data, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_animated(4, 0).or_fail()?;
+            let mut writer = encoder.write_header().or_fail()?;
+            let frames = [
+                (
+                    0,
+                    &[red, blue][..],
+                    png::BlendOp::Source,
+                    png::DisposeOp::None,
+                ),
+                (1, &[green], png::BlendOp::Over, png::DisposeOp::Previous),
+                (
+                    0,
+                    &[white],
+                    png::BlendOp::Source,
+                    png::DisposeOp::Background,
+                ),
+                (1, &[clear], png::BlendOp::Over, png::DisposeOp::None),
+            ];
+            for (x, pixels, blend, dispose) in frames {
+                writer
+                    .set_frame_dimension(pixels.len() as u32, 1)
+                    .or_fail()?;
+                writer.set_frame_position(x, 0).or_fail()?;
+                writer.set_blend_op(blend).or_fail()?;
+                writer.set_dispose_op(dispose).or_fail()?;
+                writer.set_frame_delay(1, 10).or_fail()?;
+                writer.write_image_data(&to_rgba_bytes(pixels)).or_fail()?;
+            }
+            writer.finish().or_fail()?;
+        }
+
+        let frames = decode_animation_frames(&png_data).or_fail()?;
+        let frames = frames.iter().map(colors).collect::<Vec<_>>();
+        assert_eq!(
+            frames,
+            [
+                vec![red, blue],
+                vec![red, green.alpha_blend(blue)],
+                vec![white, blue],
+                vec![clear, blue],
+            ]
+        );
+
+        let models = Models::from_png(&png_data).or_fail()?;
+        assert_eq!(models.config.animation.enabled_frame_count(), 4);
+        assert_eq!(models.config.animation.fps(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn to_indexed_works() {
+        for (n, depth, bits) in [
+            (2_usize, png::BitDepth::One, 1_usize),
+            (4, png::BitDepth::Two, 2),
+            (16, png::BitDepth::Four, 4),
+            (256, png::BitDepth::Eight, 8),
+        ] {
+            let width = 3;
+            let height = n.div_ceil(width);
+            let color = |i: usize| Rgba::new(i as u8, 1, 2, if i == 0 { 0 } else { 255 });
+            let frame = (0..width * height)
+                .flat_map(|i| {
+                    let c = color(i % n);
+                    [c.r, c.g, c.b, c.a]
+                })
+                .collect::<Vec<_>>();
+
+            let indexed = to_indexed(width, &[frame]).expect("indexed");
+            assert_eq!(indexed.depth, depth);
+            assert_eq!(indexed.palette.len(), n * 3);
+            assert_eq!(indexed.trns, [0]);
+
+            let stride = (width * bits).div_ceil(8);
+            let data = &indexed.frames[0];
+            assert_eq!(data.len(), stride * height);
+            for i in 0..width * height {
+                let (x, y) = (i % width, i / width);
+                let byte = data[y * stride + x * bits / 8];
+                let shift = 8 - bits * (x % (8 / bits) + 1);
+                let index = usize::from((byte >> shift) & (u8::MAX >> (8 - bits)));
+                let alpha = indexed.trns.get(index).copied().unwrap_or(u8::MAX);
+                let palette = &indexed.palette[index * 3..][..3];
+                assert_eq!(
+                    Rgba::new(palette[0], palette[1], palette[2], alpha),
+                    color(i % n),
+                    "n={n}, x={x}, y={y}"
+                );
+            }
+        }
+
+        let frame = (0..257).flat_map(|i| [i as u8, (i / 256) as u8, 0, 255]);
+        assert!(to_indexed(257, &[frame.collect()]).is_none());
+    }
+}
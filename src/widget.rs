@@ -5,27 +5,37 @@ use pagurus::spatial::{Position, Region, Size};
 
 pub mod block;
 pub mod bottom_bar;
+pub mod brush_box;
 pub mod button;
 pub mod color_config;
 pub mod color_palette;
 pub mod color_selector;
 pub mod config;
+pub mod fill_box;
+pub mod frame_box;
 pub mod frame_size;
+pub mod gradient_box;
+pub mod history;
 pub mod hsv_selector;
+pub mod layer_box;
 pub mod manipulate;
 pub mod manipulate_tool;
 pub mod move_camera;
 pub mod move_frame;
 pub mod number_box;
+pub mod palette_editor;
 pub mod pixel_canvas;
 pub mod pixel_size;
 pub mod preview;
 pub mod rgb_selector;
 pub mod save_load;
 pub mod select_box;
+pub mod shape_box;
 pub mod side_bar;
 pub mod size_box;
 pub mod slider;
+pub mod sprite_sheet;
+pub mod symmetry_box;
 pub mod toggle;
 pub mod tool_box;
 pub mod undo_redo;
@@ -1,12 +1,14 @@
 use super::move_frame::MoveFrameWidget;
 use super::{
-    FixedSizeWidget, VariableSizeWidget, Widget, color_config::ColorConfigWidget,
-    tool_box::ToolBoxWidget,
+    FixedSizeWidget, VariableSizeWidget, Widget, brush_box::BrushBoxWidget,
+    color_config::ColorConfigWidget, fill_box::FillBoxWidget, frame_box::FrameBoxWidget,
+    gradient_box::GradientBoxWidget, layer_box::LayerBoxWidget, shape_box::ShapeBoxWidget,
+    symmetry_box::SymmetryBoxWidget, tool_box::ToolBoxWidget,
 };
 use crate::{app::App, event::Event};
 use orfail::{OrFail, Result};
 use pagurus::image::Canvas;
-use pagurus::spatial::Region;
+use pagurus::spatial::{Position, Region};
 
 const MARGIN: u32 = 16;
 
@@ -14,7 +16,14 @@ const MARGIN: u32 = 16;
 pub struct BottomBarWidget {
     region: Region,
     move_frame: MoveFrameWidget,
+    frame_box: FrameBoxWidget,
     tool_box: ToolBoxWidget,
+    shape_box: ShapeBoxWidget,
+    brush_box: BrushBoxWidget,
+    fill_box: FillBoxWidget,
+    gradient_box: GradientBoxWidget,
+    symmetry_box: SymmetryBoxWidget,
+    layer_box: LayerBoxWidget,
     color_config: ColorConfigWidget,
 }
 
@@ -26,16 +35,34 @@ impl Widget for BottomBarWidget {
     fn render(&self, app: &App, canvas: &mut Canvas) {
         if app.models().config.animation.is_enabled() {
             self.move_frame.render_if_need(app, canvas);
+            self.frame_box.render_if_need(app, canvas);
         }
         self.tool_box.render_if_need(app, canvas);
+        self.shape_box.render_if_need(app, canvas);
+        self.brush_box.render_if_need(app, canvas);
+        self.fill_box.render_if_need(app, canvas);
+        self.gradient_box.render_if_need(app, canvas);
+        self.symmetry_box.render_if_need(app, canvas);
+        if app.models().config.layer.is_enabled() {
+            self.layer_box.render_if_need(app, canvas);
+        }
         self.color_config.render_if_need(app, canvas);
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
         if app.models().config.animation.is_enabled() {
             self.move_frame.handle_event(app, event).or_fail()?;
+            self.frame_box.handle_event(app, event).or_fail()?;
         }
         self.tool_box.handle_event(app, event).or_fail()?;
+        self.shape_box.handle_event(app, event).or_fail()?;
+        self.brush_box.handle_event(app, event).or_fail()?;
+        self.fill_box.handle_event(app, event).or_fail()?;
+        self.gradient_box.handle_event(app, event).or_fail()?;
+        self.symmetry_box.handle_event(app, event).or_fail()?;
+        if app.models().config.layer.is_enabled() {
+            self.layer_box.handle_event(app, event).or_fail()?;
+        }
         self.color_config.handle_event(app, event).or_fail()?;
         Ok(())
     }
@@ -43,7 +70,14 @@ impl Widget for BottomBarWidget {
     fn children(&mut self) -> Vec<&mut dyn Widget> {
         vec![
             &mut self.move_frame,
+            &mut self.frame_box,
             &mut self.tool_box,
+            &mut self.shape_box,
+            &mut self.brush_box,
+            &mut self.fill_box,
+            &mut self.gradient_box,
+            &mut self.symmetry_box,
+            &mut self.layer_box,
             &mut self.color_config,
         ]
     }
@@ -64,6 +98,80 @@ impl VariableSizeWidget for BottomBarWidget {
         tool_box_position.x = region.size.width as i32 / 2 - tool_box_size.width as i32 / 2;
         self.tool_box.set_position(app, tool_box_position);
 
+        let shape_box_size = self.shape_box.requiring_size(app);
+        let shape_box_position = Position::from_xy(
+            region.size.width as i32 / 2 - shape_box_size.width as i32 / 2,
+            tool_box_position.y - MARGIN as i32 / 2 - shape_box_size.height as i32,
+        );
+        self.shape_box.set_position(app, shape_box_position);
+
+        let brush_box_size = self.brush_box.requiring_size(app);
+        let brush_box_position = Position::from_xy(
+            region.size.width as i32 / 2 - brush_box_size.width as i32 / 2,
+            shape_box_position.y - MARGIN as i32 / 2 - brush_box_size.height as i32,
+        );
+        self.brush_box.set_position(app, brush_box_position);
+
+        let fill_box_size = self.fill_box.requiring_size(app);
+        let fill_box_position = Position::from_xy(
+            region.size.width as i32 / 2 - fill_box_size.width as i32 / 2,
+            tool_box_position.y - MARGIN as i32 / 2 - fill_box_size.height as i32,
+        );
+        self.fill_box.set_position(app, fill_box_position);
+
+        let gradient_box_size = self.gradient_box.requiring_size(app);
+        let gradient_box_position = Position::from_xy(
+            region.size.width as i32 / 2 - gradient_box_size.width as i32 / 2,
+            fill_box_position.y - MARGIN as i32 / 2 - gradient_box_size.height as i32,
+        );
+        self.gradient_box.set_position(app, gradient_box_position);
+
+        // The frame and layer boxes are placed beside the centered boxes if the screen is wide enough,
+        // otherwise they are stacked above them
+        let frame_box_size = self.frame_box.requiring_size(app);
+        let layer_box_size = self.layer_box.requiring_size(app);
+        let center_width = [
+            shape_box_size,
+            brush_box_size,
+            fill_box_size,
+            gradient_box_size,
+        ]
+        .into_iter()
+        .map(|size| size.width)
+        .max()
+        .unwrap_or(0);
+        let side_by_side = frame_box_size.width + center_width + layer_box_size.width + MARGIN * 4
+            <= region.size.width;
+        let row_y = if side_by_side {
+            self.region.position.y
+        } else {
+            brush_box_position.y.min(gradient_box_position.y)
+        };
+
+        let layer_box_position = Position::from_xy(
+            region.size.width as i32 - MARGIN as i32 - layer_box_size.width as i32,
+            row_y - MARGIN as i32 / 2 - layer_box_size.height as i32,
+        );
+        self.layer_box.set_position(app, layer_box_position);
+
+        let frame_box_bottom = if side_by_side {
+            row_y
+        } else {
+            layer_box_position.y
+        };
+        let frame_box_position = Position::from_xy(
+            MARGIN as i32,
+            frame_box_bottom - MARGIN as i32 / 2 - frame_box_size.height as i32,
+        );
+        self.frame_box.set_position(app, frame_box_position);
+
+        let symmetry_box_size = self.symmetry_box.requiring_size(app);
+        let symmetry_box_position = Position::from_xy(
+            MARGIN as i32,
+            frame_box_position.y - MARGIN as i32 / 2 - symmetry_box_size.height as i32,
+        );
+        self.symmetry_box.set_position(app, symmetry_box_position);
+
         let mut color_config_position = self.region.position;
         color_config_position.x = region.size.width as i32
             - MARGIN as i32
@@ -0,0 +1,205 @@
+use super::{
+    FixedSizeWidget, VariableSizeWidget, Widget, block::BlockWidget, button::ButtonWidget,
+    number_box::NumberBoxWidget, select_box::SelectBoxWidget,
+};
+use crate::{
+    app::App,
+    asset::ButtonKind,
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    model::tool::{BrushKind, Shape, ToolKind, ToolModel},
+    pixel::{Pixel, PixelPosition, PixelRegion},
+};
+use orfail::{OrFail, Result};
+use pagurus::image::Canvas;
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+const MAX_BRUSH_SIZE: u16 = 64;
+
+#[derive(Debug)]
+pub struct BrushBoxWidget {
+    region: Region,
+    kinds: SelectBoxWidget,
+    diameter: BlockWidget<NumberBoxWidget>,
+    current: BrushKind,
+    visible: bool,
+}
+
+impl BrushBoxWidget {
+    fn handle_kind_change(&mut self, app: &mut App) -> Result<()> {
+        self.kinds
+            .on_selected(|state, button| {
+                if state.is_selected() {
+                    let next = BrushKind::from_icon(button.icon()).or_fail()?;
+                    button.set_kind(ButtonKind::BasicPressed);
+
+                    if next == BrushKind::Custom && self.current != next {
+                        let custom = Self::capture_custom_brush(app);
+                        app.models_mut().tool.brush.custom = custom;
+                    }
+                    self.current = next;
+                    app.models_mut().tool.brush.kind = next;
+                } else {
+                    button.set_kind(ButtonKind::Basic);
+                }
+                app.request_redraw(button.region());
+                Ok(())
+            })
+            .or_fail()
+    }
+
+    // Uses the selected pixels (centered on the cursor) as the brush
+    fn capture_custom_brush(app: &App) -> Vec<Pixel> {
+        let models = app.models();
+        let mut region = PixelRegion::from_positions(models.selection.iter().copied());
+        let size = region.size();
+        region.end.x = region.start.x + size.width.min(MAX_BRUSH_SIZE) as i16;
+        region.end.y = region.start.y + size.height.min(MAX_BRUSH_SIZE) as i16;
+        let size = region.size();
+        let center = region.start.move_x(size.width as i16 / 2);
+        let center = center.move_y(size.height as i16 / 2);
+        region
+            .pixels()
+            .filter(|p| models.selection.contains(p))
+            .filter_map(|p| {
+                let color = models.pixel_canvas.get_direct_pixel(p)?;
+                let offset = PixelPosition::from_xy(p.x - center.x, p.y - center.y);
+                Some(Pixel::new(offset, color))
+            })
+            .collect()
+    }
+
+    fn is_brush_available(app: &App) -> bool {
+        let tool = &app.models().tool;
+        match tool.current {
+            ToolKind::Draw => tool.draw.shape == Shape::Stroke,
+            ToolKind::Erase => tool.erase.shape == Shape::Stroke,
+            _ => false,
+        }
+    }
+}
+
+impl Default for BrushBoxWidget {
+    fn default() -> Self {
+        let mut buttons = BrushKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let mut button = ButtonWidget::new(ButtonKind::Basic, kind.icon());
+                if kind == BrushKind::Custom {
+                    button.set_disabled_callback(|app| app.models().selection.is_empty());
+                }
+                button
+            })
+            .collect::<Vec<_>>();
+        buttons[0].set_kind(ButtonKind::BasicPressed);
+
+        let brush = ToolModel::default().brush;
+        Self {
+            region: Default::default(),
+            kinds: SelectBoxWidget::new(buttons, 0).expect("unreachable"),
+            diameter: BlockWidget::new(
+                "DIAMETER".parse().expect("unreachable"),
+                NumberBoxWidget::new(1, u32::from(brush.diameter), u32::from(MAX_BRUSH_SIZE)),
+            ),
+            current: brush.kind,
+            visible: false,
+        }
+    }
+}
+
+impl Widget for BrushBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        if !self.visible {
+            return;
+        }
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        self.kinds.render_if_need(app, canvas);
+        self.diameter.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.kinds.handle_event(app, event).or_fail()?;
+        self.handle_kind_change(app).or_fail()?;
+
+        self.diameter.handle_event(app, event).or_fail()?;
+        let diameter = self.diameter.body().value() as u8;
+        if app.models().tool.brush.diameter != diameter {
+            app.models_mut().tool.brush.diameter = diameter;
+        }
+
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
+        let visible = Self::is_brush_available(app);
+        if self.visible != visible {
+            self.visible = visible;
+            app.request_redraw(self.region);
+        }
+
+        let next = app.models().tool.brush.kind;
+        if self.current != next {
+            let i = BrushKind::ALL.iter().position(|&k| k == next).or_fail()?;
+            self.kinds.select(app, i).or_fail()?;
+            self.handle_kind_change(app).or_fail()?;
+        }
+
+        let diameter = u32::from(app.models().tool.brush.diameter);
+        if self.diameter.body().value() != diameter {
+            self.diameter.body_mut().set_value(app, diameter);
+        }
+
+        for child in self.children() {
+            child.handle_event_after(app).or_fail()?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        let mut children = self.kinds.children();
+        children.push(&mut self.diameter);
+        children
+    }
+}
+
+impl FixedSizeWidget for BrushBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let kinds = self.kinds.requiring_size(app);
+        let diameter = self.diameter.requiring_size(app);
+        Size::from_wh(
+            kinds.width + MARGIN + diameter.width,
+            kinds.height.max(diameter.height),
+        ) + (MARGIN * 2)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let height = self.region.size.height - MARGIN * 2;
+        let mut offset = position + MARGIN as i32;
+        let kinds = self.kinds.requiring_size(app);
+        self.kinds
+            .set_position(app, offset.move_y(((height - kinds.height) / 2) as i32));
+        offset.x += (kinds.width + MARGIN) as i32;
+
+        let diameter = self.diameter.requiring_size(app);
+        self.diameter.set_region(
+            app,
+            Region::new(
+                offset.move_y(((height - diameter.height) / 2) as i32),
+                diameter,
+            ),
+        );
+    }
+}
@@ -215,10 +215,8 @@ impl Widget for ButtonWidget {
                     self.state = ButtonState::Neutral;
                 }
             }
-            Event::Mouse { position, .. } => {
-                if !self.region.contains(position) {
-                    self.state = ButtonState::Neutral;
-                }
+            Event::Mouse { position, .. } if !self.region.contains(position) => {
+                self.state = ButtonState::Neutral;
             }
             _ => {}
         }
@@ -80,14 +80,14 @@ impl Widget for ColorConfigWidget {
     }
 
     fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
-        if app.models().tool.current == ToolKind::Pick {
-            if let Some(preview) = app.models().tool.pick.preview_color {
-                if preview != self.label {
-                    self.label = preview;
-                    app.request_redraw(self.color.region());
-                }
-                return Ok(());
+        if app.models().tool.current == ToolKind::Pick
+            && let Some(preview) = app.models().tool.pick.preview_color
+        {
+            if preview != self.label {
+                self.label = preview;
+                app.request_redraw(self.color.region());
             }
+            return Ok(());
         }
 
         let color = app.models().config.color.get();
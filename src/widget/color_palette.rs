@@ -56,18 +56,6 @@ impl ColorPaletteWidget {
 
         colors
     }
-
-    fn render_color_label(&self, button: &ButtonWidget, color: Rgba, canvas: &mut Canvas) {
-        let offset = button.state().offset(button.kind()).y;
-        let mut label_region = button.region();
-        label_region.position.x += 2;
-        label_region.size.width -= 4;
-
-        label_region.position.y += 2 + offset;
-        label_region.size.height -= 4 + 4;
-
-        canvas.fill_rectangle(label_region.without_margin(2), color.into());
-    }
 }
 
 impl Widget for ColorPaletteWidget {
@@ -79,15 +67,15 @@ impl Widget for ColorPaletteWidget {
         let mut canvas = canvas.mask_region(self.region);
         for (button, &color) in self.buttons.iter().zip(self.colors.iter()) {
             button.render(app, &mut canvas);
-            self.render_color_label(button, color, &mut canvas);
+            render_color_label(button, color, &mut canvas);
         }
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
-        if let Some(position) = event.position() {
-            if !self.region.contains(&position) {
-                return Ok(());
-            }
+        if let Some(position) = event.position()
+            && !self.region.contains(&position)
+        {
+            return Ok(());
         }
 
         for (button, &color) in self.buttons.iter_mut().zip(self.colors.iter()) {
@@ -123,3 +111,15 @@ impl FixedSizeWidget for ColorPaletteWidget {
         }
     }
 }
+
+pub fn render_color_label(button: &ButtonWidget, color: Rgba, canvas: &mut Canvas) {
+    let offset = button.state().offset(button.kind()).y;
+    let mut label_region = button.region();
+    label_region.position.x += 2;
+    label_region.size.width -= 4;
+
+    label_region.position.y += 2 + offset;
+    label_region.size.height -= 4 + 4;
+
+    canvas.fill_rectangle(label_region.without_margin(2), color.into());
+}
@@ -1,8 +1,8 @@
 use super::color_palette::ColorPaletteWidget;
 use super::{
     FixedSizeWidget, VariableSizeWidget, Widget, block::BlockWidget,
-    hsv_selector::HsvSelectorWidget, rgb_selector, rgb_selector::RgbSelectorWidget,
-    slider::SliderWidget, toggle::ToggleWidget,
+    hsv_selector::HsvSelectorWidget, palette_editor::PaletteEditorWidget, rgb_selector,
+    rgb_selector::RgbSelectorWidget, slider::SliderWidget, toggle::ToggleWidget,
 };
 use crate::{app::App, canvas_ext::CanvasExt, color, event::Event};
 use orfail::{OrFail, Result};
@@ -21,6 +21,7 @@ pub struct ColorSelectorWidget {
     rgb: BlockWidget<RgbSelectorWidget>,
     alpha: BlockWidget<SliderWidget>,
     palette: BlockWidget<ColorPaletteWidget>,
+    indexed: BlockWidget<PaletteEditorWidget>,
     replace: BlockWidget<ToggleWidget>,
     background: BlockWidget<ToggleWidget>,
 }
@@ -60,6 +61,10 @@ impl ColorSelectorWidget {
                 "PALETTE".parse().expect("unreachable"),
                 ColorPaletteWidget::new(app, width),
             ),
+            indexed: BlockWidget::new(
+                "INDEXED PALETTE".parse().expect("unreachable"),
+                PaletteEditorWidget::new(app, width),
+            ),
             replace: BlockWidget::new(
                 "REPLACE OLD COLOR PIXELS".parse().expect("unreachable"),
                 ToggleWidget::default_off(),
@@ -101,10 +106,10 @@ impl ColorSelectorWidget {
 
     fn cancel_color_replace_if_need(&mut self, app: &mut App) -> Result<()> {
         if self.replaced {
-            let config = app.models().config.clone();
-            app.models_mut()
+            let models = app.models_mut();
+            models
                 .pixel_canvas
-                .undo_command(&config)
+                .undo_command(&mut models.config)
                 .or_fail()?;
             app.request_redraw(app.screen_size().to_region());
             self.replaced = false;
@@ -146,6 +151,7 @@ impl Widget for ColorSelectorWidget {
         if !self.palette.body().is_empty() {
             self.palette.render_if_need(app, canvas);
         }
+        self.indexed.render_if_need(app, canvas);
         self.replace.render_if_need(app, canvas);
         self.background.render_if_need(app, canvas);
     }
@@ -164,9 +170,17 @@ impl Widget for ColorSelectorWidget {
             app.models_mut().config.color.set(c);
         }
 
+        // Only the HSV / RGB / ALPHA controls edit the selected indexed palette entry
+        let edited = app.models().config.color.get() != old_color;
+
         if !self.palette.body().is_empty() {
+            let color = app.models().config.color.get();
             self.palette.handle_event(app, event).or_fail()?;
+            if color != app.models().config.color.get() {
+                self.indexed.body_mut().deselect(app);
+            }
         }
+        self.indexed.handle_event(app, event).or_fail()?;
 
         let old_replace_mode = self.replace.body().is_on();
         self.replace.handle_event(app, event).or_fail()?;
@@ -182,6 +196,12 @@ impl Widget for ColorSelectorWidget {
                 app.request_redraw(self.region);
             }
         }
+        if edited {
+            self.indexed
+                .body_mut()
+                .update_selected_color(app, !new_replace_mode)
+                .or_fail()?;
+        }
 
         let old_background_mode = self.background.body().is_on();
         self.background.handle_event(app, event).or_fail()?;
@@ -203,6 +223,7 @@ impl Widget for ColorSelectorWidget {
             &mut self.hsv,
             &mut self.alpha,
             &mut self.palette,
+            &mut self.indexed,
             &mut self.replace,
             &mut self.background,
         ]
@@ -221,6 +242,7 @@ impl FixedSizeWidget for ColorSelectorWidget {
         } else {
             self.palette.requiring_size(app)
         };
+        let indexed = self.indexed.requiring_size(app);
         let replace = self.replace.requiring_size(app);
         let background = self.background.requiring_size(app);
 
@@ -231,6 +253,7 @@ impl FixedSizeWidget for ColorSelectorWidget {
                 .max(hsv.width)
                 .max(alpha.width)
                 .max(palette.width)
+                .max(indexed.width)
                 .max(replace.width + MARGIN + background.width),
             preview.height
                 + MARGIN
@@ -242,6 +265,8 @@ impl FixedSizeWidget for ColorSelectorWidget {
                 + MARGIN
                 + palette.height
                 + MARGIN
+                + indexed.height
+                + MARGIN
                 + replace.height.max(background.height),
         )
     }
@@ -269,6 +294,10 @@ impl FixedSizeWidget for ColorSelectorWidget {
 
             offset.y = self.palette.region().end().y + MARGIN as i32;
         }
+        self.indexed
+            .set_region(app, Region::new(offset, self.indexed.requiring_size(app)));
+
+        offset.y = self.indexed.region().end().y + MARGIN as i32;
         let replace_region = Region::new(offset, self.replace.requiring_size(app));
         self.replace.set_region(app, replace_region);
 
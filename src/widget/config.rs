@@ -3,7 +3,14 @@ use super::{
     FixedSizeWidget, VariableSizeWidget, Widget, block::BlockWidget, number_box::NumberBoxWidget,
     pixel_size::PixelSizeWidget, toggle::ToggleWidget,
 };
-use crate::{app::App, event::Event, model::config::Animation, region_ext::RegionExt};
+use crate::{
+    app::App,
+    event::Event,
+    model::config::{
+        Animation, BlendMode, FrameDurations, LayerSetting, MaxUndos, OnionSkin, SavedUndos,
+    },
+    region_ext::RegionExt,
+};
 use orfail::{OrFail, Result};
 use pagurus::image::Canvas;
 use pagurus::spatial::{Position, Region, Size};
@@ -17,7 +24,9 @@ pub struct ConfigWidget {
 
     // Size settings
     frame_size: BlockWidget<FrameSizeWidget>,
+    tiling: BlockWidget<ToggleWidget>,
     pixel_size: BlockWidget<PixelSizeWidget>,
+    pixel_perfect: BlockWidget<ToggleWidget>,
 
     // Preview settings
     frame_preview: BlockWidget<ToggleWidget>,
@@ -28,10 +37,32 @@ pub struct ConfigWidget {
     layer_enable: BlockWidget<ToggleWidget>,
     animation_enable: BlockWidget<ToggleWidget>,
     fps: BlockWidget<NumberBoxWidget>,
+    current_frame: usize,
+    frame_delay: BlockWidget<NumberBoxWidget>,
     apng: BlockWidget<ToggleWidget>,
+    onion_frames: BlockWidget<NumberBoxWidget>,
+    onion_opacity: BlockWidget<NumberBoxWidget>,
+    onion_tint: BlockWidget<ToggleWidget>,
+
+    // Current layer settings
+    current_layer: usize,
+    layer_hidden: BlockWidget<ToggleWidget>,
+    layer_locked: BlockWidget<ToggleWidget>,
+    layer_opacity: BlockWidget<NumberBoxWidget>,
+    blend_multiply: BlockWidget<ToggleWidget>,
+    blend_screen: BlockWidget<ToggleWidget>,
+    blend_add: BlockWidget<ToggleWidget>,
 
     // Gesture settings
     gesture: BlockWidget<ToggleWidget>,
+
+    // Export settings
+    indexed_png: BlockWidget<ToggleWidget>,
+
+    // Undo settings
+    max_undos: BlockWidget<NumberBoxWidget>,
+    saved_undos: BlockWidget<NumberBoxWidget>,
+    undo_memory: BlockWidget<NumberBoxWidget>,
 }
 
 impl ConfigWidget {
@@ -42,7 +73,24 @@ impl ConfigWidget {
         let layer = app.models().config.layer;
         let animation = app.models().config.animation;
         let apng = app.models().config.apng();
+        let onion_skin = app.models().config.onion_skin;
         let gesture = app.models().config.gesture;
+        let pixel_perfect = app.models().config.pixel_perfect;
+        let tiling = app.models().config.tiling;
+        let indexed_png = app.models().config.indexed_png;
+        let max_undos = app.models().config.max_undos;
+        let saved_undos = app.models().config.saved_undos;
+        let undo_memory = app.models().pixel_canvas.command_log_memory_usage();
+        let current_frame = app.models().config.camera.current_frame(app);
+        let frame_delay = app
+            .models()
+            .config
+            .frame_durations
+            .get_millis(current_frame)
+            .unwrap_or(0);
+        let current_layer = app.models().config.camera.current_layer(app);
+        let layer_setting = app.models().config.layer_settings.get(current_layer);
+        let blend_mode = layer_setting.blend_mode;
         Self {
             region: Region::default(),
 
@@ -51,10 +99,18 @@ impl ConfigWidget {
                 "FRAME SIZE".parse().expect("unreachable"),
                 FrameSizeWidget::new(app),
             ),
+            tiling: BlockWidget::new(
+                "TILING".parse().expect("unreachable"),
+                ToggleWidget::new(tiling),
+            ),
             pixel_size: BlockWidget::new(
                 "TOOL SIZE".parse().expect("unreachable"),
                 PixelSizeWidget::new(app),
             ),
+            pixel_perfect: BlockWidget::new(
+                "PIXEL PERFECT".parse().expect("unreachable"),
+                ToggleWidget::new(pixel_perfect),
+            ),
 
             // Preview
             frame_preview: BlockWidget::new(
@@ -87,17 +143,133 @@ impl ConfigWidget {
                     Animation::MAX_FPS as u32,
                 ),
             ),
+            current_frame,
+            frame_delay: BlockWidget::new(
+                "DELAY".parse().expect("unreachable"),
+                NumberBoxWidget::new(0, frame_delay as u32, FrameDurations::MAX_MILLIS as u32),
+            ),
             apng: BlockWidget::new(
                 "APNG".parse().expect("unreachable"),
                 ToggleWidget::new(apng),
             ),
 
+            // Onion skin
+            onion_frames: BlockWidget::new(
+                "ONION SKIN".parse().expect("unreachable"),
+                NumberBoxWidget::new(
+                    0,
+                    u32::from(onion_skin.frames()),
+                    u32::from(OnionSkin::MAX_FRAMES),
+                ),
+            ),
+            onion_opacity: BlockWidget::new(
+                "ONION OPACITY".parse().expect("unreachable"),
+                NumberBoxWidget::new(0, u32::from(onion_skin.opacity()), 100),
+            ),
+            onion_tint: BlockWidget::new(
+                "ONION TINT".parse().expect("unreachable"),
+                ToggleWidget::new(onion_skin.tint),
+            ),
+
+            // Current layer
+            current_layer,
+            layer_hidden: BlockWidget::new(
+                "HIDE".parse().expect("unreachable"),
+                ToggleWidget::new(layer_setting.hidden),
+            ),
+            layer_locked: BlockWidget::new(
+                "LOCK".parse().expect("unreachable"),
+                ToggleWidget::new(layer_setting.locked),
+            ),
+            layer_opacity: BlockWidget::new(
+                "OPACITY".parse().expect("unreachable"),
+                NumberBoxWidget::new(
+                    0,
+                    layer_setting.opacity() as u32,
+                    LayerSetting::MAX_OPACITY as u32,
+                ),
+            ),
+            blend_multiply: BlockWidget::new(
+                "MULTIPLY".parse().expect("unreachable"),
+                ToggleWidget::new(blend_mode == BlendMode::Multiply),
+            ),
+            blend_screen: BlockWidget::new(
+                "SCREEN".parse().expect("unreachable"),
+                ToggleWidget::new(blend_mode == BlendMode::Screen),
+            ),
+            blend_add: BlockWidget::new(
+                "ADD".parse().expect("unreachable"),
+                ToggleWidget::new(blend_mode == BlendMode::Add),
+            ),
+
             // Gesture
             gesture: BlockWidget::new(
                 "GESTURE".parse().expect("unreachable"),
                 ToggleWidget::new(gesture),
             ),
+
+            // Export
+            indexed_png: BlockWidget::new(
+                "INDEXED PNG".parse().expect("unreachable"),
+                ToggleWidget::new(indexed_png),
+            ),
+
+            // Undo
+            max_undos: BlockWidget::new(
+                "UNDO LIMIT".parse().expect("unreachable"),
+                NumberBoxWidget::new(MaxUndos::MIN, max_undos.get(), MaxUndos::MAX),
+            ),
+            saved_undos: BlockWidget::new(
+                "SAVED UNDOS".parse().expect("unreachable"),
+                NumberBoxWidget::new(0, u32::from(saved_undos.get()), u32::from(SavedUndos::MAX)),
+            ),
+            undo_memory: BlockWidget::new(
+                "UNDO MEMORY KB".parse().expect("unreachable"),
+                NumberBoxWidget::read_only((undo_memory / 1024) as u32),
+            ),
+        }
+    }
+
+    fn handle_layer_setting_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        let layer = self.current_layer;
+        let prev = app.models().config.layer_settings.get(layer).clone();
+
+        self.layer_hidden.handle_event(app, event).or_fail()?;
+        self.layer_locked.handle_event(app, event).or_fail()?;
+        self.layer_opacity.handle_event(app, event).or_fail()?;
+        self.blend_multiply.handle_event(app, event).or_fail()?;
+        self.blend_screen.handle_event(app, event).or_fail()?;
+        self.blend_add.handle_event(app, event).or_fail()?;
+
+        let toggles = [
+            (BlendMode::Multiply, self.blend_multiply.body().is_on()),
+            (BlendMode::Screen, self.blend_screen.body().is_on()),
+            (BlendMode::Add, self.blend_add.body().is_on()),
+        ];
+        let blend_mode = toggles
+            .iter()
+            .find(|(mode, on)| *on && *mode != prev.blend_mode)
+            .or_else(|| toggles.iter().find(|(_, on)| *on))
+            .map_or(BlendMode::Normal, |(mode, _)| *mode);
+        self.blend_multiply
+            .body_mut()
+            .set_on(app, blend_mode == BlendMode::Multiply);
+        self.blend_screen
+            .body_mut()
+            .set_on(app, blend_mode == BlendMode::Screen);
+        self.blend_add
+            .body_mut()
+            .set_on(app, blend_mode == BlendMode::Add);
+
+        let setting = app.models_mut().config.layer_settings.get_mut(layer);
+        setting.hidden = self.layer_hidden.body().is_on();
+        setting.locked = self.layer_locked.body().is_on();
+        setting.set_opacity(self.layer_opacity.body().value() as u8);
+        setting.blend_mode = blend_mode;
+        if prev != *app.models().config.layer_settings.get(layer) {
+            app.request_redraw(app.screen_size().to_region());
         }
+        Ok(())
     }
 }
 
@@ -109,7 +281,9 @@ impl Widget for ConfigWidget {
     fn render(&self, app: &App, canvas: &mut Canvas) {
         // Size
         self.frame_size.render_if_need(app, canvas);
+        self.tiling.render_if_need(app, canvas);
         self.pixel_size.render_if_need(app, canvas);
+        self.pixel_perfect.render_if_need(app, canvas);
 
         // Preview
         self.frame_preview.render_if_need(app, canvas);
@@ -120,10 +294,30 @@ impl Widget for ConfigWidget {
         self.layer_enable.render_if_need(app, canvas);
         self.animation_enable.render_if_need(app, canvas);
         self.fps.render_if_need(app, canvas);
+        self.frame_delay.render_if_need(app, canvas);
         self.apng.render_if_need(app, canvas);
 
+        // Onion skin
+        self.onion_frames.render_if_need(app, canvas);
+        self.onion_opacity.render_if_need(app, canvas);
+        self.onion_tint.render_if_need(app, canvas);
+
+        // Current layer
+        self.layer_hidden.render_if_need(app, canvas);
+        self.layer_locked.render_if_need(app, canvas);
+        self.layer_opacity.render_if_need(app, canvas);
+        self.blend_multiply.render_if_need(app, canvas);
+        self.blend_screen.render_if_need(app, canvas);
+        self.blend_add.render_if_need(app, canvas);
+
         // Gesture
         self.gesture.render_if_need(app, canvas);
+
+        // Export
+        self.indexed_png.render_if_need(app, canvas);
+        self.max_undos.render_if_need(app, canvas);
+        self.saved_undos.render_if_need(app, canvas);
+        self.undo_memory.render_if_need(app, canvas);
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
@@ -138,11 +332,20 @@ impl Widget for ConfigWidget {
             app.request_redraw(app.screen_size().to_region());
         }
 
+        let tiling = app.models().config.tiling;
+        self.tiling.handle_event(app, event).or_fail()?;
+        app.models_mut().config.tiling = self.tiling.body().is_on();
+        if tiling != app.models().config.tiling {
+            app.request_redraw(app.screen_size().to_region());
+        }
+
         self.pixel_size.handle_event(app, event).or_fail()?;
         app.models_mut()
             .config
             .minimum_pixel_size
             .set(self.pixel_size.body().value());
+        self.pixel_perfect.handle_event(app, event).or_fail()?;
+        app.models_mut().config.pixel_perfect = self.pixel_perfect.body().is_on();
 
         // Preview
         self.frame_preview.handle_event(app, event).or_fail()?;
@@ -185,6 +388,12 @@ impl Widget for ConfigWidget {
             .animation
             .set_fps(self.fps.body().value() as u8);
 
+        self.frame_delay.handle_event(app, event).or_fail()?;
+        app.models_mut()
+            .config
+            .frame_durations
+            .set_millis(self.current_frame, self.frame_delay.body().value() as u16);
+
         self.apng.handle_event(app, event).or_fail()?;
         app.models_mut().config.apng = Some(self.apng.body().is_on());
 
@@ -192,10 +401,45 @@ impl Widget for ConfigWidget {
             app.request_redraw(app.screen_size().to_region());
         }
 
+        // Onion skin
+        let onion_skin = app.models().config.onion_skin;
+        self.onion_frames.handle_event(app, event).or_fail()?;
+        app.models_mut()
+            .config
+            .onion_skin
+            .set_frames(self.onion_frames.body().value() as u8);
+        self.onion_opacity.handle_event(app, event).or_fail()?;
+        app.models_mut()
+            .config
+            .onion_skin
+            .set_opacity(self.onion_opacity.body().value() as u8);
+        self.onion_tint.handle_event(app, event).or_fail()?;
+        app.models_mut().config.onion_skin.tint = self.onion_tint.body().is_on();
+        if onion_skin != app.models().config.onion_skin {
+            app.request_redraw(app.screen_size().to_region());
+        }
+
+        // Current layer
+        self.handle_layer_setting_event(app, event).or_fail()?;
+
         // Gesture
         self.gesture.handle_event(app, event).or_fail()?;
         app.models_mut().config.gesture = self.gesture.body().is_on();
 
+        // Export
+        self.indexed_png.handle_event(app, event).or_fail()?;
+        app.models_mut().config.indexed_png = self.indexed_png.body().is_on();
+        self.max_undos.handle_event(app, event).or_fail()?;
+        app.models_mut()
+            .config
+            .max_undos
+            .set(self.max_undos.body().value());
+        self.saved_undos.handle_event(app, event).or_fail()?;
+        app.models_mut()
+            .config
+            .saved_undos
+            .set(self.saved_undos.body().value() as u16);
+
         Ok(())
     }
 
@@ -203,7 +447,9 @@ impl Widget for ConfigWidget {
         vec![
             // Size
             &mut self.frame_size,
+            &mut self.tiling,
             &mut self.pixel_size,
+            &mut self.pixel_perfect,
             // Preview
             &mut self.frame_preview,
             &mut self.frame_preview_scale,
@@ -212,20 +458,42 @@ impl Widget for ConfigWidget {
             &mut self.layer_enable,
             &mut self.animation_enable,
             &mut self.fps,
+            &mut self.frame_delay,
             &mut self.apng,
+            // Onion skin
+            &mut self.onion_frames,
+            &mut self.onion_opacity,
+            &mut self.onion_tint,
+            // Current layer
+            &mut self.layer_hidden,
+            &mut self.layer_locked,
+            &mut self.layer_opacity,
+            &mut self.blend_multiply,
+            &mut self.blend_screen,
+            &mut self.blend_add,
             // Gesture
             &mut self.gesture,
+            // Export
+            &mut self.indexed_png,
+            &mut self.max_undos,
+            &mut self.saved_undos,
+            &mut self.undo_memory,
         ]
     }
 }
 
 impl FixedSizeWidget for ConfigWidget {
     fn requiring_size(&self, app: &App) -> Size {
-        // Frame size
-        let row1 = self.frame_size.requiring_size(app);
+        // Frame size / tiling / export
+        let mut row1 = self.frame_size.requiring_size(app);
+        row1.width += MARGIN_X + self.tiling.requiring_size(app).width;
+        let indexed_png = self.indexed_png.requiring_size(app);
+        row1.width += MARGIN_X + indexed_png.width;
+        row1.height = row1.height.max(indexed_png.height);
 
         // Pixel size / gesture
         let mut row2 = self.pixel_size.requiring_size(app);
+        row2.width += MARGIN_X + self.pixel_perfect.requiring_size(app).width;
         row2.width += MARGIN_X + self.gesture.requiring_size(app).width;
 
         // Preview
@@ -237,11 +505,52 @@ impl FixedSizeWidget for ConfigWidget {
         let mut row4 = self.layer_enable.requiring_size(app);
         row4.width += MARGIN_X + self.animation_enable.requiring_size(app).width;
         row4.width += MARGIN_X + self.fps.requiring_size(app).width;
+        row4.width += MARGIN_X + self.frame_delay.requiring_size(app).width;
         row4.width += MARGIN_X + self.apng.requiring_size(app).width;
 
+        // Onion skin
+        let mut row5 = self.onion_frames.requiring_size(app);
+        row5.width += MARGIN_X + self.onion_opacity.requiring_size(app).width;
+        row5.width += MARGIN_X + self.onion_tint.requiring_size(app).width;
+
+        // Current layer
+        let mut row6 = self.layer_hidden.requiring_size(app);
+        row6.width += MARGIN_X + self.layer_locked.requiring_size(app).width;
+        row6.width += MARGIN_X + self.layer_opacity.requiring_size(app).width;
+
+        let mut row7 = self.blend_multiply.requiring_size(app);
+        row7.width += MARGIN_X + self.blend_screen.requiring_size(app).width;
+        row7.width += MARGIN_X + self.blend_add.requiring_size(app).width;
+
+        // Undo
+        let mut row8 = self.max_undos.requiring_size(app);
+        row8.width += MARGIN_X + self.saved_undos.requiring_size(app).width;
+        row8.width += MARGIN_X + self.undo_memory.requiring_size(app).width;
+
         Size::from_wh(
-            row1.width.max(row2.width).max(row3.width).max(row4.width),
-            row1.height + MARGIN_Y + row2.height + MARGIN_Y + row3.height + MARGIN_Y + row4.height,
+            row1.width
+                .max(row2.width)
+                .max(row3.width)
+                .max(row4.width)
+                .max(row5.width)
+                .max(row6.width)
+                .max(row7.width)
+                .max(row8.width),
+            row1.height
+                + MARGIN_Y
+                + row2.height
+                + MARGIN_Y
+                + row3.height
+                + MARGIN_Y
+                + row4.height
+                + MARGIN_Y
+                + row5.height
+                + MARGIN_Y
+                + row6.height
+                + MARGIN_Y
+                + row7.height
+                + MARGIN_Y
+                + row8.height,
         ) + MARGIN_X * 2
     }
 
@@ -250,18 +559,40 @@ impl FixedSizeWidget for ConfigWidget {
 
         let mut region = self.region.without_margin(MARGIN_X);
 
-        // Size, gesture
+        // Size, tiling, export, gesture
         let mut frame_size_region = region;
         frame_size_region.size = self.frame_size.requiring_size(app);
         self.frame_size.set_region(app, frame_size_region);
-        region.consume_y(frame_size_region.size.height + MARGIN_Y);
+
+        let mut tiling_region = region;
+        tiling_region.position.x = frame_size_region.end().x + MARGIN_X as i32;
+        tiling_region.size = self.tiling.requiring_size(app);
+        self.tiling.set_region(app, tiling_region);
+
+        let mut indexed_png_region = region;
+        indexed_png_region.position.x = tiling_region.end().x + MARGIN_X as i32;
+        indexed_png_region.size = self.indexed_png.requiring_size(app);
+        self.indexed_png.set_region(app, indexed_png_region);
+
+        region.consume_y(
+            frame_size_region
+                .size
+                .height
+                .max(indexed_png_region.size.height)
+                + MARGIN_Y,
+        );
 
         let mut pixel_size_region = region;
         pixel_size_region.size = self.pixel_size.requiring_size(app);
         self.pixel_size.set_region(app, pixel_size_region);
 
+        let mut pixel_perfect_region = region;
+        pixel_perfect_region.position.x = pixel_size_region.end().x + MARGIN_X as i32;
+        pixel_perfect_region.size = self.pixel_perfect.requiring_size(app);
+        self.pixel_perfect.set_region(app, pixel_perfect_region);
+
         let mut gesture_region = region;
-        gesture_region.position.x = pixel_size_region.end().x + MARGIN_X as i32;
+        gesture_region.position.x = pixel_perfect_region.end().x + MARGIN_X as i32;
         gesture_region.size = self.gesture.requiring_size(app);
         self.gesture.set_region(app, gesture_region);
 
@@ -301,9 +632,81 @@ impl FixedSizeWidget for ConfigWidget {
         fps_region.size = self.fps.requiring_size(app);
         self.fps.set_region(app, fps_region);
 
+        let mut frame_delay_region = region;
+        frame_delay_region.position.x = fps_region.end().x + MARGIN_X as i32;
+        frame_delay_region.size = self.frame_delay.requiring_size(app);
+        self.frame_delay.set_region(app, frame_delay_region);
+
         let mut apng_region = region;
-        apng_region.position.x = fps_region.end().x + MARGIN_X as i32;
+        apng_region.position.x = frame_delay_region.end().x + MARGIN_X as i32;
         apng_region.size = self.apng.requiring_size(app);
         self.apng.set_region(app, apng_region);
+
+        region.consume_y(layer_enable_region.size.height + MARGIN_Y);
+
+        // Onion skin
+        let mut onion_frames_region = region;
+        onion_frames_region.size = self.onion_frames.requiring_size(app);
+        self.onion_frames.set_region(app, onion_frames_region);
+
+        let mut onion_opacity_region = region;
+        onion_opacity_region.position.x = onion_frames_region.end().x + MARGIN_X as i32;
+        onion_opacity_region.size = self.onion_opacity.requiring_size(app);
+        self.onion_opacity.set_region(app, onion_opacity_region);
+
+        let mut onion_tint_region = region;
+        onion_tint_region.position.x = onion_opacity_region.end().x + MARGIN_X as i32;
+        onion_tint_region.size = self.onion_tint.requiring_size(app);
+        self.onion_tint.set_region(app, onion_tint_region);
+
+        region.consume_y(onion_frames_region.size.height + MARGIN_Y);
+
+        // Current layer
+        let mut layer_hidden_region = region;
+        layer_hidden_region.size = self.layer_hidden.requiring_size(app);
+        self.layer_hidden.set_region(app, layer_hidden_region);
+
+        let mut layer_locked_region = region;
+        layer_locked_region.position.x = layer_hidden_region.end().x + MARGIN_X as i32;
+        layer_locked_region.size = self.layer_locked.requiring_size(app);
+        self.layer_locked.set_region(app, layer_locked_region);
+
+        let mut layer_opacity_region = region;
+        layer_opacity_region.position.x = layer_locked_region.end().x + MARGIN_X as i32;
+        layer_opacity_region.size = self.layer_opacity.requiring_size(app);
+        self.layer_opacity.set_region(app, layer_opacity_region);
+
+        region.consume_y(layer_opacity_region.size.height + MARGIN_Y);
+
+        let mut blend_multiply_region = region;
+        blend_multiply_region.size = self.blend_multiply.requiring_size(app);
+        self.blend_multiply.set_region(app, blend_multiply_region);
+
+        let mut blend_screen_region = region;
+        blend_screen_region.position.x = blend_multiply_region.end().x + MARGIN_X as i32;
+        blend_screen_region.size = self.blend_screen.requiring_size(app);
+        self.blend_screen.set_region(app, blend_screen_region);
+
+        let mut blend_add_region = region;
+        blend_add_region.position.x = blend_screen_region.end().x + MARGIN_X as i32;
+        blend_add_region.size = self.blend_add.requiring_size(app);
+        self.blend_add.set_region(app, blend_add_region);
+
+        region.consume_y(blend_add_region.size.height + MARGIN_Y);
+
+        // Undo
+        let mut max_undos_region = region;
+        max_undos_region.size = self.max_undos.requiring_size(app);
+        self.max_undos.set_region(app, max_undos_region);
+
+        let mut saved_undos_region = region;
+        saved_undos_region.position.x = max_undos_region.end().x + MARGIN_X as i32;
+        saved_undos_region.size = self.saved_undos.requiring_size(app);
+        self.saved_undos.set_region(app, saved_undos_region);
+
+        let mut undo_memory_region = region;
+        undo_memory_region.position.x = saved_undos_region.end().x + MARGIN_X as i32;
+        undo_memory_region.size = self.undo_memory.requiring_size(app);
+        self.undo_memory.set_region(app, undo_memory_region);
     }
 }
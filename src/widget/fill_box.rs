@@ -0,0 +1,211 @@
+use super::{
+    FixedSizeWidget, VariableSizeWidget, Widget,
+    block::BlockWidget,
+    button::ButtonWidget,
+    number_box::NumberBoxWidget,
+    select_box::{ItemState, SelectBoxWidget},
+};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    model::tool::{FillBound, FillToolState, ToolKind, ToolModel},
+};
+use orfail::{OrFail, Result};
+use pagurus::image::Canvas;
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug)]
+pub struct FillBoxWidget {
+    region: Region,
+    mode: SelectBoxWidget,
+    connectivity: SelectBoxWidget,
+    bound: ButtonWidget,
+    tolerance: BlockWidget<NumberBoxWidget>,
+    current: FillToolState,
+    visible: bool,
+}
+
+impl FillBoxWidget {
+    fn select_box(icons: [IconId; 2], selected: bool) -> SelectBoxWidget {
+        let mut buttons = icons
+            .into_iter()
+            .map(|icon| ButtonWidget::new(ButtonKind::Basic, icon))
+            .collect::<Vec<_>>();
+        let i = usize::from(selected);
+        buttons[i].set_kind(ButtonKind::BasicPressed);
+        SelectBoxWidget::new(buttons, i).expect("unreachable")
+    }
+
+    fn handle_select(app: &mut App, state: ItemState, button: &mut ButtonWidget) -> Result<()> {
+        if state.is_selected() {
+            button.set_kind(ButtonKind::BasicPressed);
+        } else {
+            button.set_kind(ButtonKind::Basic);
+        }
+        app.request_redraw(button.region());
+        Ok(())
+    }
+
+    fn handle_options_change(&mut self, app: &mut App) -> Result<()> {
+        self.mode
+            .on_selected(|state, button| Self::handle_select(app, state, button))
+            .or_fail()?;
+        self.connectivity
+            .on_selected(|state, button| Self::handle_select(app, state, button))
+            .or_fail()?;
+
+        let fill = &mut app.models_mut().tool.fill;
+        fill.global = self.mode.selected() == 1;
+        fill.diagonal = self.connectivity.selected() == 1;
+        fill.bound = self.current.bound;
+        fill.tolerance = self.tolerance.body().value() as u8;
+        self.current = fill.clone();
+        Ok(())
+    }
+}
+
+impl Default for FillBoxWidget {
+    fn default() -> Self {
+        Self {
+            region: Default::default(),
+            mode: Self::select_box([IconId::Bucket, IconId::FillGlobal], false),
+            connectivity: Self::select_box([IconId::FillConnect4, IconId::FillConnect8], false),
+            bound: ButtonWidget::new(ButtonKind::Basic, FillBound::default().icon()),
+            tolerance: BlockWidget::new(
+                "TOLERANCE".parse().expect("unreachable"),
+                NumberBoxWidget::new(0, 0, u32::from(u8::MAX)),
+            ),
+            current: ToolModel::default().fill,
+            visible: false,
+        }
+    }
+}
+
+impl Widget for FillBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        if !self.visible {
+            return;
+        }
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        self.mode.render_if_need(app, canvas);
+        self.connectivity.render_if_need(app, canvas);
+        self.bound.render_if_need(app, canvas);
+        self.tolerance.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.mode.handle_event(app, event).or_fail()?;
+        self.connectivity.handle_event(app, event).or_fail()?;
+        self.bound.handle_event(app, event).or_fail()?;
+        if self.bound.take_clicked(app) {
+            self.current.bound = self.current.bound.next();
+            self.bound.set_icon(app, self.current.bound.icon());
+        }
+        self.tolerance.handle_event(app, event).or_fail()?;
+        self.handle_options_change(app).or_fail()?;
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
+        let visible = matches!(
+            app.models().tool.current,
+            ToolKind::Fill | ToolKind::Gradient
+        );
+        if self.visible != visible {
+            self.visible = visible;
+            app.request_redraw(self.region);
+        }
+
+        let fill = app.models().tool.fill.clone();
+        if self.current != fill {
+            self.mode.select(app, usize::from(fill.global)).or_fail()?;
+            self.connectivity
+                .select(app, usize::from(fill.diagonal))
+                .or_fail()?;
+            self.bound.set_icon(app, fill.bound.icon());
+            self.current.bound = fill.bound;
+            self.tolerance
+                .body_mut()
+                .set_value(app, u32::from(fill.tolerance));
+            self.handle_options_change(app).or_fail()?;
+        }
+
+        for child in self.children() {
+            child.handle_event_after(app).or_fail()?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        let mut children = self.mode.children();
+        children.extend(self.connectivity.children());
+        children.push(&mut self.bound);
+        children.push(&mut self.tolerance);
+        children
+    }
+}
+
+impl FixedSizeWidget for FillBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let mode = self.mode.requiring_size(app);
+        let connectivity = self.connectivity.requiring_size(app);
+        let bound = self.bound.requiring_size(app);
+        let tolerance = self.tolerance.requiring_size(app);
+        Size::from_wh(
+            mode.width
+                + MARGIN
+                + connectivity.width
+                + MARGIN
+                + bound.width
+                + MARGIN
+                + tolerance.width,
+            mode.height.max(tolerance.height),
+        ) + (MARGIN * 2)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let height = self.region.size.height - MARGIN * 2;
+        let mut offset = position + MARGIN as i32;
+        let mode = self.mode.requiring_size(app);
+        self.mode
+            .set_position(app, offset.move_y(((height - mode.height) / 2) as i32));
+        offset.x += (mode.width + MARGIN) as i32;
+
+        let connectivity = self.connectivity.requiring_size(app);
+        self.connectivity.set_position(
+            app,
+            offset.move_y(((height - connectivity.height) / 2) as i32),
+        );
+        offset.x += (connectivity.width + MARGIN) as i32;
+
+        let bound = self.bound.requiring_size(app);
+        self.bound
+            .set_position(app, offset.move_y(((height - bound.height) / 2) as i32));
+        offset.x += (bound.width + MARGIN) as i32;
+
+        let tolerance = self.tolerance.requiring_size(app);
+        self.tolerance.set_region(
+            app,
+            Region::new(
+                offset.move_y(((height - tolerance.height) / 2) as i32),
+                tolerance,
+            ),
+        );
+    }
+}
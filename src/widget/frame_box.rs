@@ -0,0 +1,226 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+};
+use orfail::{OrFail, Result};
+use pagurus::{
+    event::Key,
+    image::Canvas,
+    spatial::{Position, Region, Size},
+};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameOp {
+    Insert,
+    Duplicate,
+    Delete,
+    MoveLeft,
+    MoveRight,
+}
+
+impl FrameOp {
+    const ALL: [Self; 5] = [
+        Self::Insert,
+        Self::Duplicate,
+        Self::Delete,
+        Self::MoveLeft,
+        Self::MoveRight,
+    ];
+
+    fn icon(self) -> IconId {
+        match self {
+            Self::Insert => IconId::FrameInsert,
+            Self::Duplicate => IconId::FrameDuplicate,
+            Self::Delete => IconId::FrameDelete,
+            Self::MoveLeft => IconId::FrameMoveLeft,
+            Self::MoveRight => IconId::FrameMoveRight,
+        }
+    }
+
+    fn key(self) -> char {
+        match self {
+            Self::Insert => '+',
+            Self::Duplicate => '*',
+            Self::Delete => '-',
+            Self::MoveLeft => '[',
+            Self::MoveRight => ']',
+        }
+    }
+
+    fn is_disabled(self, app: &App) -> bool {
+        let frames = app.models().config.animation.frame_count() as usize;
+        let current = app.models().config.camera.current_frame(app);
+        match self {
+            Self::Insert | Self::Duplicate => false,
+            Self::Delete => frames < 2,
+            Self::MoveLeft => current == 0,
+            Self::MoveRight => current + 1 >= frames,
+        }
+    }
+
+    fn disabled_callback(self) -> Option<fn(&App) -> bool> {
+        match self {
+            Self::Insert | Self::Duplicate => None,
+            Self::Delete => Some(|app| Self::Delete.is_disabled(app)),
+            Self::MoveLeft => Some(|app| Self::MoveLeft.is_disabled(app)),
+            Self::MoveRight => Some(|app| Self::MoveRight.is_disabled(app)),
+        }
+    }
+
+    // Returns the source frame of each frame after the operation and the frame to be focused
+    fn rearrange(self, current: usize, frames: usize) -> (Vec<Option<usize>>, usize) {
+        let mut sources = (0..frames).map(Some).collect::<Vec<_>>();
+        let focus = match self {
+            Self::Insert => {
+                sources.insert(current + 1, None);
+                current + 1
+            }
+            Self::Duplicate => {
+                sources.insert(current + 1, Some(current));
+                current + 1
+            }
+            Self::Delete => {
+                sources.remove(current);
+                current.min(sources.len() - 1)
+            }
+            Self::MoveLeft => {
+                sources.swap(current - 1, current);
+                current - 1
+            }
+            Self::MoveRight => {
+                sources.swap(current, current + 1);
+                current + 1
+            }
+        };
+        (sources, focus)
+    }
+
+    fn apply(self, app: &mut App) -> Result<()> {
+        if self.is_disabled(app) {
+            return Ok(());
+        }
+
+        let frames = app.models().config.animation.frame_count() as usize;
+        let current = app.models().config.camera.current_frame(app);
+        let (sources, focus) = self.rearrange(current, frames);
+        let models = app.models_mut();
+        models
+            .pixel_canvas
+            .rearrange_frames(&mut models.config, &sources)
+            .or_fail()?;
+
+        let width = app.models().config.frame.get_base_region().size().width as i32;
+        let delta = Position::from_xy((focus as i32 - current as i32) * width, 0);
+        let delta = delta * app.models().config.zoom.get() as u32;
+        app.models_mut().config.camera.r#move(delta);
+        app.request_redraw(app.screen_size().to_region());
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct FrameBoxWidget {
+    region: Region,
+    buttons: Vec<(FrameOp, ButtonWidget)>,
+}
+
+impl FrameBoxWidget {
+    fn handle_key_event(&mut self, event: &mut Event) -> Option<FrameOp> {
+        let Event::Key { event, consumed } = event else {
+            return None;
+        };
+        let Key::Char(c) = event.key else {
+            return None;
+        };
+        if event.ctrl || event.alt {
+            return None;
+        }
+
+        let op = FrameOp::ALL.into_iter().find(|op| op.key() == c)?;
+        *consumed = true;
+        Some(op)
+    }
+}
+
+impl Default for FrameBoxWidget {
+    fn default() -> Self {
+        let buttons = FrameOp::ALL
+            .into_iter()
+            .map(|op| {
+                let mut button = ButtonWidget::new(ButtonKind::Basic, op.icon());
+                if let Some(f) = op.disabled_callback() {
+                    button.set_disabled_callback(f);
+                }
+                (op, button)
+            })
+            .collect();
+        Self {
+            region: Region::default(),
+            buttons,
+        }
+    }
+}
+
+impl Widget for FrameBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        for (_, button) in &self.buttons {
+            button.render_if_need(app, canvas);
+        }
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if let Some(op) = self.handle_key_event(event) {
+            op.apply(app).or_fail()?;
+        }
+
+        for (op, button) in &mut self.buttons {
+            button.handle_event(app, event).or_fail()?;
+            if button.take_clicked(app) {
+                op.apply(app).or_fail()?;
+            }
+        }
+
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        self.buttons
+            .iter_mut()
+            .map(|(_, button)| button as &mut dyn Widget)
+            .collect()
+    }
+}
+
+impl FixedSizeWidget for FrameBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let n = self.buttons.len() as u32;
+        let button_size = self.buttons[0].1.requiring_size(app);
+        Size::from_wh(
+            button_size.width * n + MARGIN * (n + 1),
+            button_size.height + MARGIN * 2,
+        )
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let mut offset = position + MARGIN as i32;
+        for (_, button) in &mut self.buttons {
+            button.set_position(app, offset);
+            offset.x += (button.requiring_size(app).width + MARGIN) as i32;
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget, select_box::SelectBoxWidget};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    marker::gradient::gray_level,
+    model::tool::{DitherPattern, ToolKind},
+    pixel::{PixelPosition, PixelRegion},
+    region_ext::RegionExt,
+};
+use orfail::{OrFail, Result};
+use pagurus::image::{Canvas, Rgba};
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+const MAX_CUSTOM_PATTERN_SIZE: u16 = 32;
+
+#[derive(Debug)]
+pub struct GradientBoxWidget {
+    region: Region,
+    patterns: SelectBoxWidget,
+    color: ButtonWidget,
+    current: DitherPattern,
+    label: Rgba,
+    visible: bool,
+}
+
+impl GradientBoxWidget {
+    fn handle_pattern_change(&mut self, app: &mut App) -> Result<()> {
+        self.patterns
+            .on_selected(|state, button| {
+                if state.is_selected() {
+                    let next = DitherPattern::from_icon(button.icon()).or_fail()?;
+                    button.set_kind(ButtonKind::BasicPressed);
+
+                    if next == DitherPattern::Custom && self.current != next {
+                        let custom = Self::capture_custom_pattern(app);
+                        app.models_mut().tool.gradient.custom = custom;
+                    }
+                    self.current = next;
+                    app.models_mut().tool.gradient.pattern = next;
+                } else {
+                    button.set_kind(ButtonKind::Basic);
+                }
+                app.request_redraw(button.region());
+                Ok(())
+            })
+            .or_fail()
+    }
+
+    // Uses the gray levels of the selected pixels as the threshold map
+    fn capture_custom_pattern(app: &App) -> Vec<Vec<u8>> {
+        let models = app.models();
+        let mut region = PixelRegion::from_positions(models.selection.iter().copied());
+        let size = region.size();
+        region.end.x = region.start.x + size.width.min(MAX_CUSTOM_PATTERN_SIZE) as i16;
+        region.end.y = region.start.y + size.height.min(MAX_CUSTOM_PATTERN_SIZE) as i16;
+        (region.start.y..region.end.y)
+            .map(|y| {
+                (region.start.x..region.end.x)
+                    .map(|x| {
+                        let position = PixelPosition::from_xy(x, y);
+                        models
+                            .pixel_canvas
+                            .get_direct_pixel(position)
+                            .filter(|_| models.selection.contains(&position))
+                            .map_or(0, gray_level)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn render_color_label(&self, canvas: &mut Canvas) {
+        let offset = self.color.state().offset(self.color.kind()).y;
+        let mut label_region = self.color.region();
+        label_region.position.x += 4;
+        label_region.size.width -= 8;
+
+        label_region.position.y += 4 + offset;
+        label_region.size.height -= 4 + 12;
+
+        canvas.fill_rectangle(label_region.without_margin(2), self.label.into());
+    }
+}
+
+impl Default for GradientBoxWidget {
+    fn default() -> Self {
+        let mut buttons = DitherPattern::ALL
+            .into_iter()
+            .map(|pattern| {
+                let mut button = ButtonWidget::new(ButtonKind::Basic, pattern.icon());
+                if pattern == DitherPattern::Custom {
+                    button.set_disabled_callback(|app| app.models().selection.is_empty());
+                }
+                button
+            })
+            .collect::<Vec<_>>();
+        let selected = DitherPattern::ALL
+            .iter()
+            .position(|&p| p == DitherPattern::default())
+            .expect("unreachable");
+        buttons[selected].set_kind(ButtonKind::BasicPressed);
+
+        Self {
+            region: Default::default(),
+            patterns: SelectBoxWidget::new(buttons, selected).expect("unreachable"),
+            color: ButtonWidget::new(ButtonKind::Basic, IconId::Null),
+            current: DitherPattern::default(),
+            label: Rgba::new(0, 0, 0, 0),
+            visible: false,
+        }
+    }
+}
+
+impl Widget for GradientBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        if !self.visible {
+            return;
+        }
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        self.patterns.render_if_need(app, canvas);
+        self.color.render_if_need(app, canvas);
+        self.render_color_label(canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.patterns.handle_event(app, event).or_fail()?;
+        self.handle_pattern_change(app).or_fail()?;
+
+        // Swap the drawing color and the end color
+        self.color.handle_event(app, event).or_fail()?;
+        if self.color.take_clicked(app) {
+            let models = app.models_mut();
+            let start = models.config.color.get();
+            models.config.color.set(models.tool.gradient.color);
+            models.tool.gradient.color = start;
+        }
+
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
+        let visible = app.models().tool.current == ToolKind::Gradient;
+        if self.visible != visible {
+            self.visible = visible;
+            app.request_redraw(self.region);
+        }
+
+        let next = app.models().tool.gradient.pattern;
+        if self.current != next {
+            let i = DitherPattern::ALL
+                .iter()
+                .position(|&p| p == next)
+                .or_fail()?;
+            self.patterns.select(app, i).or_fail()?;
+            self.handle_pattern_change(app).or_fail()?;
+        }
+
+        let label = app.models().tool.gradient.color;
+        if self.label != label {
+            self.label = label;
+            app.request_redraw(self.color.region());
+        }
+
+        for child in self.children() {
+            child.handle_event_after(app).or_fail()?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        let mut children = self.patterns.children();
+        children.push(&mut self.color);
+        children
+    }
+}
+
+impl FixedSizeWidget for GradientBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let patterns = self.patterns.requiring_size(app);
+        let color = self.color.requiring_size(app);
+        Size::from_wh(
+            patterns.width + MARGIN + color.width,
+            patterns.height.max(color.height),
+        ) + (MARGIN * 2)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let offset = position + MARGIN as i32;
+        self.patterns.set_position(app, offset);
+        let x = (self.patterns.requiring_size(app).width + MARGIN) as i32;
+        self.color.set_position(app, offset.move_x(x));
+    }
+}
@@ -0,0 +1,274 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    pixel::{PixelPosition, PixelRegion},
+};
+use orfail::{OrFail, Result};
+use pagurus::image::{Canvas, Rgba};
+use pagurus::spatial::{Position, Region, Size};
+
+const ROWS: usize = 6;
+const MARGIN: u32 = 8;
+const THUMBNAIL_SIZE: u32 = 64;
+const COUNT_WIDTH: u32 = 80;
+
+#[derive(Debug)]
+pub struct HistoryWidget {
+    region: Region,
+    entries: Vec<ButtonWidget>,
+    branches: Vec<ButtonWidget>,
+    newer: ButtonWidget,
+    older: ButtonWidget,
+
+    // The state index shown in the first row (`0` is the state before any command)
+    top: usize,
+}
+
+impl HistoryWidget {
+    pub fn new(app: &App) -> Self {
+        let canvas = &app.models().pixel_canvas;
+        let len = canvas.command_log().len();
+        let top = (canvas.command_log_tail() + ROWS / 2)
+            .min(len)
+            .max(len.min(ROWS - 1));
+        let entries = (0..ROWS)
+            .map(|row| {
+                let (kind, icon) = top
+                    .checked_sub(row)
+                    .map_or((ButtonKind::Basic, IconId::Null), |i| {
+                        Self::entry_style(app, i)
+                    });
+                ButtonWidget::new(kind, icon)
+            })
+            .collect();
+        Self {
+            region: Region::default(),
+            entries,
+            branches: (0..ROWS)
+                .map(|_| ButtonWidget::new(ButtonKind::Basic, IconId::GoRight))
+                .collect(),
+            newer: ButtonWidget::new(ButtonKind::Basic, IconId::GoTop),
+            older: ButtonWidget::new(ButtonKind::Basic, IconId::GoBottom),
+            top,
+        }
+    }
+
+    fn entry_style(app: &App, i: usize) -> (ButtonKind, IconId) {
+        let canvas = &app.models().pixel_canvas;
+        let icon = i
+            .checked_sub(1)
+            .and_then(|i| canvas.command_log()[i].tool)
+            .map_or(IconId::Null, |tool| tool.icon());
+        if i == canvas.command_log_tail() {
+            (ButtonKind::BasicPressed, icon)
+        } else {
+            (ButtonKind::Basic, icon)
+        }
+    }
+
+    fn state_index(&self, row: usize) -> Option<usize> {
+        self.top.checked_sub(row)
+    }
+
+    fn has_branch(&self, app: &App, row: usize) -> bool {
+        self.state_index(row)
+            .is_some_and(|i| app.models().pixel_canvas.branch_count(i) > 0)
+    }
+
+    fn update_entries(&mut self, app: &mut App) {
+        for row in 0..ROWS {
+            let Some(i) = self.state_index(row) else {
+                continue;
+            };
+            let (kind, icon) = Self::entry_style(app, i);
+            let button = &mut self.entries[row];
+            button.set_kind(kind);
+            button.set_icon(app, icon);
+        }
+        app.request_redraw(self.region);
+    }
+
+    fn render_thumbnail(&self, app: &App, canvas: &mut Canvas, row: usize, region: Region) {
+        canvas.fill_rectangle(region, color::PREVIEW_BACKGROUND);
+        canvas.draw_rectangle(region, color::PREVIEW_BORDER);
+
+        let Some(i) = self.state_index(row).and_then(|i| i.checked_sub(1)) else {
+            return;
+        };
+        let command = &app.models().pixel_canvas.command_log()[i];
+        let pixel_region = PixelRegion::from_positions(command.positions());
+        let size = pixel_region.size();
+        let inner = Region::new(
+            region.position + 2,
+            Size::square(region.size.width.saturating_sub(4)),
+        );
+        let side = u32::from(size.width.max(size.height)).max(1);
+        let unit = (inner.size.width / side).max(1);
+        let mut canvas = canvas.mask_region(inner);
+        let mut fill = |position: PixelPosition, color: Rgba| {
+            let x = (position.x - pixel_region.start.x) as u32 * inner.size.width / side;
+            let y = (position.y - pixel_region.start.y) as u32 * inner.size.width / side;
+            let offset = Position::from_xy(x as i32, y as i32);
+            canvas.fill_rectangle(
+                Region::new(inner.position + offset, Size::square(unit)),
+                color.into(),
+            );
+        };
+        for pixel in &command.erase {
+            fill(pixel.position, Rgba::new(200, 200, 200, 255));
+        }
+        for pixel in &command.draw {
+            fill(pixel.position, pixel.color);
+        }
+    }
+
+    fn render_row(&self, app: &App, canvas: &mut Canvas, row: usize) {
+        let Some(i) = self.state_index(row) else {
+            return;
+        };
+        let button = &self.entries[row];
+        button.render_if_need(app, canvas);
+
+        let mut thumbnail_region = button.region();
+        thumbnail_region.position.x = button.region().end().x + MARGIN as i32;
+        thumbnail_region.size = Size::square(THUMBNAIL_SIZE);
+        self.render_thumbnail(app, canvas, row, thumbnail_region);
+
+        let pixel_count = i.checked_sub(1).map_or(0, |i| {
+            app.models().pixel_canvas.command_log()[i].pixel_count()
+        });
+        let mut number_position = thumbnail_region.position;
+        number_position.x = thumbnail_region.end().x + (MARGIN + COUNT_WIDTH) as i32 - 10;
+        number_position.y += (THUMBNAIL_SIZE / 2) as i32 - 7;
+        canvas.draw_number(
+            number_position,
+            pixel_count as u32,
+            &app.assets().digits_10x14,
+        );
+
+        // Dim the entries that can only be reached by redo
+        if i > app.models().pixel_canvas.command_log_tail() {
+            let mut dimmed = button.region();
+            dimmed.size.width = (number_position.x + 10 - dimmed.position.x) as u32;
+            canvas.fill_rectangle(dimmed, Rgba::new(221, 220, 213, 128).into());
+        }
+
+        if self.has_branch(app, row) {
+            self.branches[row].render_if_need(app, canvas);
+        }
+    }
+}
+
+impl Widget for HistoryWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        canvas.fill_rectangle(self.region, color::WINDOW_BACKGROUND);
+        for row in 0..ROWS {
+            self.render_row(app, canvas, row);
+        }
+        self.newer.render_if_need(app, canvas);
+        self.older.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        let len = app.models().pixel_canvas.command_log().len();
+        let top = self.top;
+        let tail = app.models().pixel_canvas.command_log_tail();
+        let mut switched = false;
+
+        self.newer.handle_event(app, event).or_fail()?;
+        if self.newer.take_clicked(app) {
+            self.top = (self.top + ROWS).min(len);
+        }
+
+        self.older.handle_event(app, event).or_fail()?;
+        if self.older.take_clicked(app) {
+            self.top = self.top.saturating_sub(ROWS).max(len.min(ROWS - 1));
+        }
+
+        for row in 0..ROWS {
+            let Some(i) = self.state_index(row) else {
+                continue;
+            };
+            self.entries[row].handle_event(app, event).or_fail()?;
+            if self.entries[row].take_clicked(app) {
+                let models = app.models_mut();
+                models
+                    .pixel_canvas
+                    .jump_to_command(&mut models.config, i)
+                    .or_fail()?;
+                app.request_redraw(app.screen_size().to_region());
+            }
+
+            if self.has_branch(app, row) {
+                self.branches[row].handle_event(app, event).or_fail()?;
+                if self.branches[row].take_clicked(app) {
+                    let models = app.models_mut();
+                    models
+                        .pixel_canvas
+                        .switch_branch(&mut models.config, i)
+                        .or_fail()?;
+                    app.request_redraw(app.screen_size().to_region());
+                    switched = true;
+                }
+            }
+        }
+
+        if switched {
+            let len = app.models().pixel_canvas.command_log().len();
+            self.top = self.top.min(len).max(len.min(ROWS - 1));
+        }
+        if switched || top != self.top || tail != app.models().pixel_canvas.command_log_tail() {
+            self.update_entries(app);
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        let mut children = vec![&mut self.newer as &mut dyn Widget, &mut self.older];
+        children.extend(self.entries.iter_mut().map(|b| b as &mut dyn Widget));
+        children.extend(self.branches.iter_mut().map(|b| b as &mut dyn Widget));
+        children
+    }
+}
+
+impl FixedSizeWidget for HistoryWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let button = self.newer.requiring_size(app);
+        let row_width =
+            button.width + MARGIN + THUMBNAIL_SIZE + MARGIN + COUNT_WIDTH + MARGIN + button.width;
+        Size::from_wh(
+            row_width + MARGIN + button.width,
+            ROWS as u32 * (button.height + MARGIN) - MARGIN,
+        ) + MARGIN * 2
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let button = self.newer.requiring_size(app);
+        let mut offset = position + MARGIN as i32;
+        let branch_x =
+            (button.width + MARGIN + THUMBNAIL_SIZE + MARGIN + COUNT_WIDTH + MARGIN) as i32;
+        for (entry, branch) in self.entries.iter_mut().zip(self.branches.iter_mut()) {
+            entry.set_position(app, offset);
+            branch.set_position(app, offset.move_x(branch_x));
+            offset.y += (button.height + MARGIN) as i32;
+        }
+
+        let x = self.region.end().x - (MARGIN + button.width) as i32;
+        self.newer
+            .set_position(app, Position::from_xy(x, position.y + MARGIN as i32));
+        self.older.set_position(
+            app,
+            Position::from_xy(x, self.region.end().y - (MARGIN + button.height) as i32),
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    model::config::Layer,
+};
+use orfail::{OrFail, Result};
+use pagurus::{
+    event::Key,
+    image::Canvas,
+    spatial::{Position, Region, Size},
+};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerOp {
+    Insert,
+    Duplicate,
+    Delete,
+    MoveUp,
+    MoveDown,
+    MergeDown,
+}
+
+impl LayerOp {
+    const ALL: [Self; 6] = [
+        Self::Insert,
+        Self::Duplicate,
+        Self::Delete,
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MergeDown,
+    ];
+
+    fn icon(self) -> IconId {
+        match self {
+            Self::Insert => IconId::LayerInsert,
+            Self::Duplicate => IconId::LayerDuplicate,
+            Self::Delete => IconId::LayerDelete,
+            Self::MoveUp => IconId::LayerMoveUp,
+            Self::MoveDown => IconId::LayerMoveDown,
+            Self::MergeDown => IconId::LayerMergeDown,
+        }
+    }
+
+    // Used with the Alt modifier
+    fn key(self) -> Key {
+        match self {
+            Self::Insert => Key::Char('+'),
+            Self::Duplicate => Key::Char('*'),
+            Self::Delete => Key::Char('-'),
+            Self::MoveUp => Key::Char(']'),
+            Self::MoveDown => Key::Char('['),
+            Self::MergeDown => Key::Down,
+        }
+    }
+
+    fn is_disabled(self, app: &App) -> bool {
+        let layers = app.models().config.layer.count() as usize;
+        let current = app.models().config.camera.current_layer(app);
+        match self {
+            Self::Insert | Self::Duplicate => layers >= usize::from(Layer::MAX),
+            Self::Delete => layers < 2,
+            Self::MoveUp => current + 1 >= layers,
+            Self::MoveDown | Self::MergeDown => current == 0,
+        }
+    }
+
+    fn disabled_callback(self) -> fn(&App) -> bool {
+        match self {
+            Self::Insert => |app| Self::Insert.is_disabled(app),
+            Self::Duplicate => |app| Self::Duplicate.is_disabled(app),
+            Self::Delete => |app| Self::Delete.is_disabled(app),
+            Self::MoveUp => |app| Self::MoveUp.is_disabled(app),
+            Self::MoveDown => |app| Self::MoveDown.is_disabled(app),
+            Self::MergeDown => |app| Self::MergeDown.is_disabled(app),
+        }
+    }
+
+    // Returns the source layers of each layer after the operation and the layer to be focused
+    // (an upper layer has a larger index)
+    fn rearrange(self, current: usize, layers: usize) -> (Vec<Vec<usize>>, usize) {
+        let mut sources = (0..layers).map(|i| vec![i]).collect::<Vec<_>>();
+        let focus = match self {
+            Self::Insert => {
+                sources.insert(current + 1, Vec::new());
+                current + 1
+            }
+            Self::Duplicate => {
+                sources.insert(current + 1, vec![current]);
+                current + 1
+            }
+            Self::Delete => {
+                sources.remove(current);
+                current.min(sources.len() - 1)
+            }
+            Self::MoveUp => {
+                sources.swap(current, current + 1);
+                current + 1
+            }
+            Self::MoveDown => {
+                sources.swap(current - 1, current);
+                current - 1
+            }
+            Self::MergeDown => {
+                sources.remove(current);
+                sources[current - 1].push(current);
+                current - 1
+            }
+        };
+        (sources, focus)
+    }
+
+    fn apply(self, app: &mut App) -> Result<()> {
+        if self.is_disabled(app) {
+            return Ok(());
+        }
+
+        let layers = app.models().config.layer.count() as usize;
+        let current = app.models().config.camera.current_layer(app);
+        let (sources, focus) = self.rearrange(current, layers);
+        let models = app.models_mut();
+        models
+            .pixel_canvas
+            .rearrange_layers(&mut models.config, &sources)
+            .or_fail()?;
+
+        let height = app.models().config.frame.get_base_region().size().height as i32;
+        let delta = Position::from_xy(0, (focus as i32 - current as i32) * height);
+        let delta = delta * app.models().config.zoom.get() as u32;
+        app.models_mut().config.camera.r#move(delta);
+        app.request_redraw(app.screen_size().to_region());
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct LayerBoxWidget {
+    region: Region,
+    buttons: Vec<(LayerOp, ButtonWidget)>,
+}
+
+impl LayerBoxWidget {
+    fn handle_key_event(&mut self, event: &mut Event) -> Option<LayerOp> {
+        let Event::Key { event, consumed } = event else {
+            return None;
+        };
+        if !event.alt {
+            return None;
+        }
+
+        let op = LayerOp::ALL.into_iter().find(|op| op.key() == event.key)?;
+        *consumed = true;
+        Some(op)
+    }
+}
+
+impl Default for LayerBoxWidget {
+    fn default() -> Self {
+        let buttons = LayerOp::ALL
+            .into_iter()
+            .map(|op| {
+                let button = ButtonWidget::new(ButtonKind::Basic, op.icon())
+                    .with_disabled_callback(op.disabled_callback());
+                (op, button)
+            })
+            .collect();
+        Self {
+            region: Region::default(),
+            buttons,
+        }
+    }
+}
+
+impl Widget for LayerBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        for (_, button) in &self.buttons {
+            button.render_if_need(app, canvas);
+        }
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if let Some(op) = self.handle_key_event(event) {
+            op.apply(app).or_fail()?;
+        }
+
+        for (op, button) in &mut self.buttons {
+            button.handle_event(app, event).or_fail()?;
+            if button.take_clicked(app) {
+                op.apply(app).or_fail()?;
+            }
+        }
+
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        self.buttons
+            .iter_mut()
+            .map(|(_, button)| button as &mut dyn Widget)
+            .collect()
+    }
+}
+
+impl FixedSizeWidget for LayerBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let n = self.buttons.len() as u32;
+        let button_size = self.buttons[0].1.requiring_size(app);
+        Size::from_wh(
+            button_size.width * n + MARGIN * (n + 1),
+            button_size.height + MARGIN * 2,
+        )
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let mut offset = position + MARGIN as i32;
+        for (_, button) in &mut self.buttons {
+            button.set_position(app, offset);
+            offset.x += (button.requiring_size(app).width + MARGIN) as i32;
+        }
+    }
+}
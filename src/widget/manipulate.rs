@@ -20,6 +20,7 @@ pub struct ManipulateWidget {
     delta: PixelPosition,
     state: State,
     tool: ManipulateToolWidget,
+    command_log_start: usize,
 }
 
 impl ManipulateWidget {
@@ -40,6 +41,7 @@ impl ManipulateWidget {
             terminated: false,
             state: State::Neutral,
             tool: ManipulateToolWidget::default(),
+            command_log_start: app.models().pixel_canvas.command_log_tail(),
         };
         this.set_region(app, app.screen_size().to_region());
         app.request_redraw(this.tool.region());
@@ -78,6 +80,7 @@ impl ManipulateWidget {
             terminated: false,
             state: State::Neutral,
             tool: ManipulateToolWidget::default(),
+            command_log_start: app.models().pixel_canvas.command_log_tail(),
         };
         this.set_region(app, app.screen_size().to_region());
         this
@@ -87,10 +90,20 @@ impl ManipulateWidget {
         &self.selected_pixels
     }
 
+    pub fn manipulated_area(&self) -> impl '_ + Iterator<Item = PixelPosition> {
+        self.manipulating_pixels
+            .keys()
+            .map(move |position| *position + self.delta)
+    }
+
     pub fn is_terminated(&self) -> bool {
         self.terminated
     }
 
+    pub fn command_log_start(&self) -> usize {
+        self.command_log_start
+    }
+
     pub fn tool_region(&self) -> Region {
         self.tool.region()
     }
@@ -133,7 +146,7 @@ impl ManipulateWidget {
     fn vertical_flip(&mut self, app: &mut App) {
         let region = PixelRegion::from_positions(self.manipulating_pixels.keys().copied());
         let center = region.center();
-        let is_even = region.size().height % 2 == 0;
+        let is_even = region.size().height.is_multiple_of(2);
         self.manipulating_pixels = self
             .manipulating_pixels
             .drain()
@@ -151,7 +164,7 @@ impl ManipulateWidget {
     fn horizontal_flip(&mut self, app: &mut App) {
         let region = PixelRegion::from_positions(self.manipulating_pixels.keys().copied());
         let center = region.center();
-        let is_even = region.size().width % 2 == 0;
+        let is_even = region.size().width.is_multiple_of(2);
         self.manipulating_pixels = self
             .manipulating_pixels
             .drain()
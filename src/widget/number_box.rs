@@ -20,6 +20,7 @@ pub struct NumberBoxWidget {
     max: u32,
     focused: bool,
     input: Option<InputId>,
+    read_only: bool,
 }
 
 impl NumberBoxWidget {
@@ -31,6 +32,14 @@ impl NumberBoxWidget {
             max,
             focused: false,
             input: None,
+            read_only: false,
+        }
+    }
+
+    pub fn read_only(value: u32) -> Self {
+        Self {
+            read_only: true,
+            ..Self::new(value, value, value)
         }
     }
 
@@ -78,6 +87,10 @@ impl Widget for NumberBoxWidget {
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+
         let prev_focused = self.focused;
         match event {
             Event::Mouse {
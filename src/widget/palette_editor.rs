@@ -0,0 +1,274 @@
+use super::{
+    FixedSizeWidget, Widget, button::ButtonWidget, color_palette::render_color_label,
+    toggle::ToggleWidget,
+};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    io::IoRequest,
+};
+use orfail::{OrFail, Result};
+use pagurus::{
+    image::{Canvas, Rgba},
+    spatial::{Position, Region, Size},
+};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug)]
+pub struct PaletteEditorWidget {
+    region: Region,
+    width: u32,
+    enabled: ToggleWidget,
+    add: ButtonWidget,
+    remove: ButtonWidget,
+    load: ButtonWidget,
+    save: ButtonWidget,
+    buttons: Vec<ButtonWidget>,
+    selected: Option<usize>,
+    old_color: Rgba,
+    replaced: bool,
+}
+
+impl PaletteEditorWidget {
+    pub fn new(app: &App, width: u32) -> Self {
+        let palette = &app.models().config.palette;
+        Self {
+            region: Region::default(),
+            width,
+            enabled: ToggleWidget::new(palette.is_enabled()),
+            add: ButtonWidget::new(ButtonKind::Middle, IconId::PaletteAdd)
+                .with_disabled_callback(|app| app.models().config.palette.is_full()),
+            remove: ButtonWidget::new(ButtonKind::Middle, IconId::PaletteRemove),
+            load: ButtonWidget::new(ButtonKind::Middle, IconId::PaletteLoad),
+            save: ButtonWidget::new(ButtonKind::Middle, IconId::PaletteSave)
+                .with_disabled_callback(|app| app.models().config.palette.colors().is_empty()),
+            buttons: palette
+                .colors()
+                .iter()
+                .map(|_| ButtonWidget::new(ButtonKind::Middle, IconId::Null))
+                .collect(),
+            selected: None,
+            old_color: Rgba::new(0, 0, 0, 0),
+            replaced: false,
+        }
+    }
+
+    pub fn update_selected_color(&mut self, app: &mut App, recolor: bool) -> Result<()> {
+        let Some(i) = self.selected else {
+            return Ok(());
+        };
+        let new_color = app.models().config.color.get();
+        if app.models().config.palette.colors().get(i) == Some(&new_color) {
+            return Ok(());
+        }
+
+        app.models_mut().config.palette.set(i, new_color);
+        app.request_redraw(self.buttons[i].region());
+        if !recolor {
+            return Ok(());
+        }
+
+        self.cancel_recolor_if_need(app).or_fail()?;
+        if new_color != self.old_color {
+            let config = app.models().config.clone();
+            let command_log_tail = app.models().pixel_canvas.command_log_tail();
+            app.models_mut()
+                .pixel_canvas
+                .replace_color(&config, self.old_color, new_color)
+                .or_fail()?;
+            if command_log_tail != app.models().pixel_canvas.command_log_tail() {
+                self.replaced = true;
+            }
+        }
+        app.request_redraw(app.screen_size().to_region());
+        Ok(())
+    }
+
+    fn cancel_recolor_if_need(&mut self, app: &mut App) -> Result<()> {
+        if self.replaced {
+            let models = app.models_mut();
+            models
+                .pixel_canvas
+                .undo_command(&mut models.config)
+                .or_fail()?;
+            self.replaced = false;
+        }
+        Ok(())
+    }
+
+    pub fn deselect(&mut self, app: &mut App) {
+        if self.selected.is_some() {
+            self.select(app, None);
+        }
+    }
+
+    fn select(&mut self, app: &mut App, selected: Option<usize>) {
+        for i in self.selected.into_iter().chain(selected) {
+            app.request_redraw(self.buttons[i].region());
+        }
+        self.selected = selected;
+        self.replaced = false;
+        if let Some(i) = selected {
+            let color = app.models().config.palette.colors()[i];
+            self.old_color = color;
+            app.models_mut().config.color.set(color);
+        }
+    }
+
+    fn sync_buttons(&mut self, app: &mut App) {
+        let n = app.models().config.palette.colors().len();
+        if self.selected.is_some_and(|i| i >= n) {
+            self.selected = None;
+        }
+        self.buttons
+            .resize_with(n, || ButtonWidget::new(ButtonKind::Middle, IconId::Null));
+        self.set_position(app, self.region.position);
+        app.request_redraw(self.region);
+    }
+
+    fn columns(&self) -> usize {
+        (self.width / ButtonKind::Middle.size().width).max(1) as usize
+    }
+}
+
+impl Widget for PaletteEditorWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        self.enabled.render_if_need(app, canvas);
+        self.add.render_if_need(app, canvas);
+        self.remove.render_if_need(app, canvas);
+        self.load.render_if_need(app, canvas);
+        self.save.render_if_need(app, canvas);
+
+        let colors = app.models().config.palette.colors();
+        for (i, (button, &color)) in self.buttons.iter().zip(colors.iter()).enumerate() {
+            button.render(app, canvas);
+            render_color_label(button, color, canvas);
+            if self.selected == Some(i) {
+                canvas.draw_rectangle(button.region(), color::PREVIEW_FOCUSED_BORDER);
+            }
+        }
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if self.buttons.len() != app.models().config.palette.colors().len() {
+            // The palette has been replaced by an imported palette file.
+            self.sync_buttons(app);
+        }
+
+        self.enabled.handle_event(app, event).or_fail()?;
+        if self.enabled.is_on() != app.models().config.palette.is_enabled() {
+            app.models_mut()
+                .config
+                .palette
+                .set_enabled(self.enabled.is_on());
+        }
+
+        self.add.handle_event(app, event).or_fail()?;
+        if self.add.take_clicked(app) {
+            let color = app.models().config.color.get();
+            if app.models_mut().config.palette.add(color) {
+                self.sync_buttons(app);
+            }
+        }
+
+        self.remove.handle_event(app, event).or_fail()?;
+        if self.remove.take_clicked(app)
+            && let Some(i) = self.selected
+        {
+            self.select(app, None);
+            app.models_mut().config.palette.remove(i);
+            self.sync_buttons(app);
+        }
+
+        self.load.handle_event(app, event).or_fail()?;
+        if self.load.take_clicked(app) {
+            app.enqueue_io_request(IoRequest::ImportPalette);
+        }
+
+        self.save.handle_event(app, event).or_fail()?;
+        if self.save.take_clicked(app) {
+            app.enqueue_io_request(IoRequest::ExportPalette);
+        }
+
+        for i in 0..self.buttons.len() {
+            self.buttons[i].handle_event(app, event).or_fail()?;
+            if self.buttons[i].take_clicked(app) {
+                if self.selected == Some(i) {
+                    self.select(app, None);
+                } else {
+                    self.select(app, Some(i));
+                }
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        let mut children: Vec<&mut dyn Widget> = vec![
+            &mut self.enabled,
+            &mut self.add,
+            &mut self.remove,
+            &mut self.load,
+            &mut self.save,
+        ];
+        children.extend(self.buttons.iter_mut().map(|b| b as &mut dyn Widget));
+        children
+    }
+}
+
+impl FixedSizeWidget for PaletteEditorWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let toggle = self.enabled.requiring_size(app);
+        let button = self.add.requiring_size(app);
+        let mut height = toggle.height.max(button.height);
+
+        let rows = self.buttons.len().div_ceil(self.columns()) as u32;
+        if rows > 0 {
+            height += MARGIN + rows * button.height;
+        }
+        Size::from_wh(self.width, height)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let toggle = self.enabled.requiring_size(app);
+        let button = self.add.requiring_size(app);
+        let row_height = toggle.height.max(button.height);
+
+        let mut offset = position;
+        offset.y += (row_height - toggle.height) as i32 / 2;
+        self.enabled.set_position(app, offset);
+
+        offset.x = self.enabled.region().end().x + MARGIN as i32;
+        offset.y = position.y + (row_height - button.height) as i32 / 2;
+        self.add.set_position(app, offset);
+
+        offset.x = self.add.region().end().x;
+        self.remove.set_position(app, offset);
+
+        offset.x = self.remove.region().end().x + MARGIN as i32;
+        self.load.set_position(app, offset);
+
+        offset.x = self.load.region().end().x;
+        self.save.set_position(app, offset);
+
+        let columns = self.columns();
+        let base = position.move_y((row_height + MARGIN) as i32);
+        for (i, b) in self.buttons.iter_mut().enumerate() {
+            let x = (i % columns) as u32 * button.width;
+            let y = (i / columns) as u32 * button.height;
+            b.set_position(app, base.move_x(x as i32).move_y(y as i32));
+        }
+    }
+}
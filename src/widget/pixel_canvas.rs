@@ -9,11 +9,11 @@ use crate::{
     gesture::{GestureEvent, GestureRecognizer},
     io::IoRequest,
     marker::{MarkerHandler, MarkerKind},
-    model::tool::{ToolKind, ToolModel},
+    model::tool::{SymmetryMode, ToolKind, ToolModel},
     pixel::{Pixel, PixelPosition, PixelRegion},
 };
 use orfail::{OrFail, Result};
-use pagurus::spatial::Region;
+use pagurus::spatial::{Position, Region};
 use pagurus::{
     image::{Canvas, Color, Rgba},
     spatial::Contains,
@@ -126,8 +126,71 @@ impl PixelCanvasWidget {
         }
     }
 
+    fn render_symmetry_axes(&self, app: &App, canvas: &mut Canvas) {
+        let symmetry = self.tool.symmetry;
+        if symmetry.mode == SymmetryMode::None {
+            return;
+        }
+
+        let config = &app.models().config;
+        let pixel_drawing_region = PixelRegion::from_screen_region(app, canvas.drawing_region());
+        let pixel_frames_region = config.frame.get_full_region(config);
+        let region = pixel_drawing_region.intersection(pixel_frames_region);
+        if region.is_empty() {
+            return;
+        }
+
+        let half = i32::from(config.zoom.get()) / 2;
+        let frame_region = config.frame.get_base_region();
+        let frame_size = frame_region.size();
+        let first_frame = (region.start.x - frame_region.start.x) / frame_size.width as i16;
+        let first_layer = (region.start.y - frame_region.start.y) / frame_size.height as i16;
+        let last_frame = (region.end.x - 1 - frame_region.start.x) / frame_size.width as i16;
+        let last_layer = (region.end.y - 1 - frame_region.start.y) / frame_size.height as i16;
+        for frame in first_frame..=last_frame {
+            for layer in first_layer..=last_layer {
+                let current = frame_region.shift_x(frame).shift_y(layer);
+                let screen_region = current.to_screen_region(app);
+
+                // The axes can lie on the middle of pixels
+                let axis = symmetry.frame_axis(frame_region, current.start);
+                let mut position =
+                    PixelPosition::from_xy(axis.x.div_euclid(2), axis.y.div_euclid(2))
+                        .to_screen_position(app);
+                position.x += i32::from(axis.x.rem_euclid(2)) * half;
+                position.y += i32::from(axis.y.rem_euclid(2)) * half;
+
+                if symmetry.mode != SymmetryMode::Vertical {
+                    canvas.draw_vertical_line(
+                        Position::from_xy(position.x, screen_region.position.y),
+                        screen_region.size.height,
+                        color::SYMMETRY_AXIS,
+                    );
+                }
+                if symmetry.mode != SymmetryMode::Horizontal {
+                    canvas.draw_horizontal_line(
+                        Position::from_xy(screen_region.position.x, position.y),
+                        screen_region.size.width,
+                        color::SYMMETRY_AXIS,
+                    );
+                }
+            }
+        }
+    }
+
     fn render_drawn_pixels(&self, app: &App, canvas: &mut Canvas) {
-        let color = app.models().config.color.get();
+        if let Some(pixels) = self.marker_handler.colored_pixels(app) {
+            for pixel in pixels {
+                let region = pixel.position.to_screen_region(app);
+                if canvas.drawing_region().intersection(region).is_empty() {
+                    continue;
+                }
+                canvas.fill_rectangle(region, pixel.color.into());
+            }
+            return;
+        }
+
+        let color = app.models().config.drawing_color();
         if self.marker_handler.is_neutral() && app.models().tool.tool_kind() != ToolKind::Fill {
             let pixel_region = PixelRegion::from_positions(self.marker_handler.marked_pixels(app));
             let region = pixel_region.to_screen_region(app);
@@ -179,10 +242,10 @@ impl PixelCanvasWidget {
         {
             let mut color = pixel.color;
             let mut alpha = 255;
-            if let Some(w) = &self.manipulate {
-                if w.selected_pixels().contains(&pixel.position) {
-                    alpha = 0;
-                }
+            if let Some(w) = &self.manipulate
+                && w.selected_pixels().contains(&pixel.position)
+            {
+                alpha = 0;
             }
 
             let region = pixel.position.to_screen_region(app);
@@ -210,6 +273,84 @@ impl PixelCanvasWidget {
         }
     }
 
+    // Ghosts the previous / next frames under the current frame
+    fn render_onion_skin(&self, app: &App, canvas: &mut Canvas) {
+        let config = &app.models().config;
+        let onion_skin = config.onion_skin;
+        let frame_count = config.animation.enabled_frame_count() as i16;
+        if onion_skin.frames() == 0 || frame_count <= 1 {
+            return;
+        }
+
+        let current_frame = config.camera.current_frame(app) as i16;
+        let current_layer = config.camera.current_layer(app) as i16;
+        let current = config
+            .frame
+            .get_base_region()
+            .shift_x(current_frame)
+            .shift_y(current_layer);
+        let region = PixelRegion::from_screen_region(app, canvas.drawing_region());
+        if current.intersection(region).is_empty() {
+            return;
+        }
+
+        // Farther frames first so that nearer ones are rendered over them
+        let n = i16::from(onion_skin.frames());
+        for distance in (1..=n).rev().flat_map(|d| [-d, d]) {
+            let frame = current_frame + distance;
+            if !(0..frame_count).contains(&frame) {
+                continue;
+            }
+            let offset = current.size().width as i16 * distance;
+            let ghost_region = current.intersection(region).move_x(offset);
+            for pixel in app.models().pixel_canvas.get_pixels(config, ghost_region) {
+                let position = pixel.position.move_x(-offset);
+                let color = onion_skin.ghost(pixel.color, distance);
+                canvas.fill_rectangle(position.to_screen_region(app), color.into());
+            }
+        }
+    }
+
+    // Renders repeated copies of the tiling frame around the frames
+    fn render_tiled_pixels(&self, app: &App, canvas: &mut Canvas) {
+        let config = &app.models().config;
+        let Some(tile) = config.tiling_region(app) else {
+            return;
+        };
+
+        let full_region = config.frame.get_full_region(config);
+        let pixel_region = PixelRegion::from_screen_region(app, canvas.drawing_region());
+        let size = tile.size();
+        let (w, h) = (size.width as i16, size.height as i16);
+        let first_x = (pixel_region.start.x - tile.start.x).div_euclid(w);
+        let first_y = (pixel_region.start.y - tile.start.y).div_euclid(h);
+        let last_x = (pixel_region.end.x - 1 - tile.start.x).div_euclid(w);
+        let last_y = (pixel_region.end.y - 1 - tile.start.y).div_euclid(h);
+
+        let pixels = app
+            .models()
+            .pixel_canvas
+            .get_pixels(config, tile)
+            .collect::<Vec<_>>();
+        for y in first_y..=last_y {
+            for x in first_x..=last_x {
+                let copy = tile.shift_x(x).shift_y(y);
+                if copy.intersection(pixel_region).is_empty()
+                    || full_region.intersection(copy) == copy
+                {
+                    continue;
+                }
+                for pixel in &pixels {
+                    let position = pixel.position.move_x(x * w).move_y(y * h);
+                    if full_region.contains(position) || !pixel_region.contains(position) {
+                        continue;
+                    }
+                    canvas.fill_rectangle(position.to_screen_region(app), pixel.color.into());
+                }
+            }
+        }
+    }
+
     fn handle_gesture(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
         let Some(gesture) = self.gesture_recognizer.handle_event(app, event).or_fail()? else {
             return Ok(());
@@ -235,16 +376,16 @@ impl PixelCanvasWidget {
                 app.request_redraw(app.screen_size().to_region());
             }
             GestureEvent::TwoFingerSwipe { undo } => {
-                let config = app.models().config.clone();
+                let models = app.models_mut();
                 if undo {
-                    app.models_mut()
+                    models
                         .pixel_canvas
-                        .undo_command(&config)
+                        .undo_command(&mut models.config)
                         .or_fail()?;
                 } else {
-                    app.models_mut()
+                    models
                         .pixel_canvas
-                        .redo_command(&config)
+                        .redo_command(&mut models.config)
                         .or_fail()?;
                 }
                 app.enqueue_io_request(IoRequest::Vibrate);
@@ -280,8 +421,15 @@ impl Widget for PixelCanvasWidget {
             self.render_frame_edges(app, canvas);
         }
 
+        if !preview_mode {
+            self.render_onion_skin(app, canvas);
+        }
         self.render_pixels(app, canvas);
-        if matches!(self.tool.tool_kind(), ToolKind::Draw | ToolKind::Fill) {
+        self.render_tiled_pixels(app, canvas);
+        if matches!(
+            self.tool.tool_kind(),
+            ToolKind::Draw | ToolKind::Fill | ToolKind::Gradient
+        ) {
             self.render_drawn_pixels(app, canvas);
         } else if self.tool.tool_kind() == ToolKind::Select
             || (self.tool.tool_kind() == ToolKind::Erase
@@ -290,6 +438,9 @@ impl Widget for PixelCanvasWidget {
         {
             self.render_selected_pixels(app, canvas);
         }
+        if !preview_mode {
+            self.render_symmetry_axes(app, canvas);
+        }
         if let Some(w) = &self.manipulate {
             w.render(app, canvas);
         } else if let Some(w) = &self.move_camera {
@@ -312,6 +463,14 @@ impl Widget for PixelCanvasWidget {
         if let Some(w) = &mut self.manipulate {
             w.handle_event(app, event).or_fail()?;
             if w.is_terminated() {
+                // Undo the whole manipulation (including copies) at once
+                let start = w.command_log_start();
+                let canvas = &mut app.models_mut().pixel_canvas;
+                canvas.coalesce_commands(start);
+                if canvas.command_log_tail() > start {
+                    canvas.set_last_command_tool(ToolKind::Select);
+                }
+                app.models_mut().selection = w.manipulated_area().collect();
                 app.request_redraw(w.region());
                 self.manipulate = None;
             }
@@ -322,16 +481,21 @@ impl Widget for PixelCanvasWidget {
         self.marker_handler.handle_event(app, event).or_fail()?;
         if self.marker_handler.take_completed() {
             let config = app.models().config.clone();
+            let command_log_tail = app.models().pixel_canvas.command_log_tail();
             match self.tool.tool_kind() {
-                ToolKind::Draw | ToolKind::Fill => {
-                    let color = app.models().config.color.get();
-                    let pixels = self
-                        .marker_handler
-                        .marked_pixels(app)
-                        .map(|pos| Pixel::new(pos, color));
+                ToolKind::Draw | ToolKind::Fill | ToolKind::Gradient => {
+                    let color = config.drawing_color();
+                    let pixels = if let Some(pixels) = self.marker_handler.colored_pixels(app) {
+                        pixels.collect::<Vec<_>>()
+                    } else {
+                        self.marker_handler
+                            .marked_pixels(app)
+                            .map(|pos| Pixel::new(pos, color))
+                            .collect()
+                    };
                     app.models_mut()
                         .pixel_canvas
-                        .draw_pixels(&config, pixels)
+                        .draw_pixels(&config, pixels.into_iter())
                         .or_fail()?;
                 }
                 ToolKind::Erase => {
@@ -344,6 +508,7 @@ impl Widget for PixelCanvasWidget {
                 ToolKind::Select => {
                     let target_pixels: HashSet<_> =
                         self.marker_handler.marked_pixels(app).collect();
+                    app.models_mut().selection = target_pixels.clone();
                     if target_pixels
                         .iter()
                         .any(|p| app.models().pixel_canvas.get_direct_pixel(*p).is_some())
@@ -366,17 +531,21 @@ impl Widget for PixelCanvasWidget {
                     }
                 }
             }
-        } else if self.tool.tool_kind() == ToolKind::Pick {
-            if let Some(position) = self.marker_handler.marked_pixels(app).next() {
-                if let Some(color) = app
-                    .models()
-                    .pixel_canvas
-                    .get_pixel(&app.models().config, position)
-                {
-                    app.models_mut().tool.pick.preview_color = Some(color);
-                } else {
-                    app.models_mut().tool.pick.preview_color = None;
-                }
+            if command_log_tail != app.models().pixel_canvas.command_log_tail() {
+                let tool = self.tool.tool_kind();
+                app.models_mut().pixel_canvas.set_last_command_tool(tool);
+            }
+        } else if self.tool.tool_kind() == ToolKind::Pick
+            && let Some(position) = self.marker_handler.marked_pixels(app).next()
+        {
+            if let Some(color) = app
+                .models()
+                .pixel_canvas
+                .get_pixel(&app.models().config, position)
+            {
+                app.models_mut().tool.pick.preview_color = Some(color);
+            } else {
+                app.models_mut().tool.pick.preview_color = None;
             }
         }
         Ok(())
@@ -388,6 +557,10 @@ impl Widget for PixelCanvasWidget {
             let dirty_region =
                 PixelRegion::from_positions(dirty_pixels.into_iter()).to_screen_region(app);
             app.request_redraw(dirty_region);
+            let config = &app.models().config;
+            if config.tiling || config.onion_skin.frames() > 0 {
+                app.request_redraw(self.region);
+            }
 
             let layers = app.models().pixel_canvas.get_layers(&app.models().config);
             if layers != app.models().config.layer.count() {
@@ -403,6 +576,9 @@ impl Widget for PixelCanvasWidget {
         }
 
         if self.tool != app.models().tool {
+            if self.tool.symmetry != app.models().tool.symmetry {
+                app.request_redraw(self.region);
+            }
             self.tool = app.models().tool.clone();
             self.marker_handler.set_marker_kind(self.tool.marker_kind());
 
@@ -413,6 +589,12 @@ impl Widget for PixelCanvasWidget {
             }
         }
 
+        let pixel_region = PixelRegion::from_screen_region(app, self.region);
+        let models = app.models_mut();
+        models
+            .pixel_canvas
+            .cache_composited_pixels(&models.config, pixel_region);
+
         for child in self.children() {
             child.handle_event_after(app).or_fail()?;
         }
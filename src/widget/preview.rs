@@ -282,8 +282,8 @@ struct Playing {
 impl Playing {
     fn start(app: &mut App) -> Self {
         let current_frame = app.models().config.camera.current_frame(app);
-        let frame_interval = app.models().config.animation.frame_interval();
-        app.set_timeout(PLAYING_TAG, frame_interval);
+        let frame_duration = app.models().config.frame_duration(current_frame);
+        app.set_timeout(PLAYING_TAG, frame_duration);
         Self { current_frame }
     }
 
@@ -291,12 +291,12 @@ impl Playing {
         let Event::Timeout(PLAYING_TAG) = event else {
             return Ok(());
         };
-        let frame_interval = app.models().config.animation.frame_interval();
-        app.set_timeout(PLAYING_TAG, frame_interval);
         self.current_frame += 1;
         if self.current_frame >= app.models().config.animation.enabled_frame_count() as usize {
             self.current_frame = 0;
         }
+        let frame_duration = app.models().config.frame_duration(self.current_frame);
+        app.set_timeout(PLAYING_TAG, frame_duration);
         app.request_redraw(preview_region);
         Ok(())
     }
@@ -7,6 +7,7 @@ use crate::{
     event::Event,
     io::IoRequest,
     region_ext::RegionExt,
+    window::sprite_sheet::SpriteSheetWindow,
 };
 use orfail::{OrFail, Result};
 use pagurus::image::Canvas;
@@ -19,6 +20,7 @@ pub struct SaveLoadWidget {
     region: Region,
     save: ButtonWidget,
     load: ButtonWidget,
+    import: ButtonWidget,
 }
 
 impl Default for SaveLoadWidget {
@@ -27,6 +29,17 @@ impl Default for SaveLoadWidget {
             region: Default::default(),
             save: ButtonWidget::new(ButtonKind::Basic, IconId::Save),
             load: ButtonWidget::new(ButtonKind::Basic, IconId::Load),
+            import: ButtonWidget::new(ButtonKind::Basic, IconId::Import),
+        }
+    }
+}
+
+impl SaveLoadWidget {
+    fn button_count(&self, app: &App) -> u32 {
+        if app.runtime_options.disable_save_workspace_button {
+            2
+        } else {
+            3
         }
     }
 }
@@ -41,6 +54,7 @@ impl Widget for SaveLoadWidget {
         canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
         self.save.render_if_need(app, canvas);
         self.load.render_if_need(app, canvas);
+        self.import.render_if_need(app, canvas);
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
@@ -54,23 +68,24 @@ impl Widget for SaveLoadWidget {
             app.enqueue_io_request(IoRequest::LoadWorkspace);
         }
 
+        self.import.handle_event(app, event).or_fail()?;
+        if self.import.take_clicked(app) {
+            app.spawn_window(SpriteSheetWindow::new(app)).or_fail()?;
+        }
+
         event.consume_if_contained(self.region);
         Ok(())
     }
 
     fn children(&mut self) -> Vec<&mut dyn Widget> {
-        vec![&mut self.save, &mut self.load]
+        vec![&mut self.save, &mut self.load, &mut self.import]
     }
 }
 
 impl FixedSizeWidget for SaveLoadWidget {
     fn requiring_size(&self, app: &App) -> Size {
         let button_size = self.save.requiring_size(app);
-        let count = if app.runtime_options.disable_save_workspace_button {
-            1
-        } else {
-            2
-        };
+        let count = self.button_count(app);
         Size::from_wh(
             button_size.width + MARGIN * 2,
             button_size.height * count + MARGIN * count * 2,
@@ -81,16 +96,15 @@ impl FixedSizeWidget for SaveLoadWidget {
         self.region = Region::new(position, self.requiring_size(app));
 
         let mut block = self.region;
-        if app.runtime_options.disable_save_workspace_button {
-            self.load
-                .set_position(app, block.without_margin(MARGIN).position);
-        } else {
-            block.size.height /= 2;
-
+        block.size.height /= self.button_count(app);
+        if !app.runtime_options.disable_save_workspace_button {
             self.save
                 .set_position(app, block.without_margin(MARGIN).position);
-            self.load
-                .set_position(app, block.shift_y(1).without_margin(MARGIN).position);
+            block = block.shift_y(1);
         }
+        self.load
+            .set_position(app, block.without_margin(MARGIN).position);
+        self.import
+            .set_position(app, block.shift_y(1).without_margin(MARGIN).position);
     }
 }
@@ -0,0 +1,138 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget, select_box::SelectBoxWidget};
+use crate::{
+    app::App,
+    asset::ButtonKind,
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    model::tool::{Shape, ToolKind},
+};
+use orfail::{OrFail, Result};
+use pagurus::image::Canvas;
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug)]
+pub struct ShapeBoxWidget {
+    region: Region,
+    shapes: SelectBoxWidget,
+    current: Shape,
+    visible: bool,
+}
+
+impl ShapeBoxWidget {
+    fn current_shape(app: &App) -> Option<Shape> {
+        let tool = &app.models().tool;
+        match tool.current {
+            ToolKind::Draw => Some(tool.draw.shape),
+            ToolKind::Erase => Some(tool.erase.shape),
+            _ => None,
+        }
+    }
+
+    fn set_current_shape(app: &mut App, shape: Shape) {
+        let tool = &mut app.models_mut().tool;
+        match tool.current {
+            ToolKind::Draw => tool.draw.shape = shape,
+            ToolKind::Erase => tool.erase.shape = shape,
+            _ => {}
+        }
+    }
+
+    fn handle_shape_change(&mut self, app: &mut App) -> Result<()> {
+        self.shapes
+            .on_selected(|state, button| {
+                if state.is_selected() {
+                    let next = Shape::from_icon(button.icon()).or_fail()?;
+                    button.set_kind(ButtonKind::BasicPressed);
+
+                    self.current = next;
+                    Self::set_current_shape(app, next);
+                } else {
+                    button.set_kind(ButtonKind::Basic);
+                }
+                app.request_redraw(button.region());
+                Ok(())
+            })
+            .or_fail()
+    }
+}
+
+impl Default for ShapeBoxWidget {
+    fn default() -> Self {
+        let mut buttons = Shape::ALL
+            .into_iter()
+            .map(|shape| ButtonWidget::new(ButtonKind::Basic, shape.icon()))
+            .collect::<Vec<_>>();
+        buttons[0].set_kind(ButtonKind::BasicPressed);
+
+        Self {
+            region: Default::default(),
+            shapes: SelectBoxWidget::new(buttons, 0).expect("unreachable"),
+            current: Shape::default(),
+            visible: true,
+        }
+    }
+}
+
+impl Widget for ShapeBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        if !self.visible {
+            return;
+        }
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        self.shapes.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.shapes.handle_event(app, event).or_fail()?;
+        self.handle_shape_change(app).or_fail()?;
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
+        let shape = Self::current_shape(app);
+        if self.visible != shape.is_some() {
+            self.visible = shape.is_some();
+            app.request_redraw(self.region);
+        }
+
+        if let Some(next) = shape
+            && self.current != next
+        {
+            let i = Shape::ALL.iter().position(|&s| s == next).or_fail()?;
+            self.shapes.select(app, i).or_fail()?;
+            self.handle_shape_change(app).or_fail()?;
+        }
+
+        for child in self.children() {
+            child.handle_event_after(app).or_fail()?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        self.shapes.children()
+    }
+}
+
+impl FixedSizeWidget for ShapeBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        self.shapes.requiring_size(app) + (MARGIN * 2)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+        self.shapes.set_position(app, position + MARGIN as i32);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{
+    FixedSizeWidget, VariableSizeWidget, Widget, block::BlockWidget, button::ButtonWidget,
+    size_box::SizeBoxWidget, toggle::ToggleWidget,
+};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    event::Event,
+    io::IoRequest,
+    pixel::PixelSize,
+    region_ext::RegionExt,
+};
+use orfail::{OrFail, Result};
+use pagurus::image::Canvas;
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug)]
+pub struct SpriteSheetWidget {
+    region: Region,
+    size: BlockWidget<SizeBoxWidget>,
+    grid: BlockWidget<ToggleWidget>,
+    rows_as_layers: BlockWidget<ToggleWidget>,
+    import: ButtonWidget,
+    requested: bool,
+}
+
+impl SpriteSheetWidget {
+    pub fn new(app: &App) -> Self {
+        let options = app.sprite_sheet_options;
+        Self {
+            region: Region::default(),
+            size: BlockWidget::new(
+                "CELL SIZE".parse().expect("unreachable"),
+                SizeBoxWidget::with_min_max(
+                    options.size,
+                    PixelSize::square(1),
+                    PixelSize::square(1024),
+                ),
+            ),
+            grid: BlockWidget::new(
+                "GRID".parse().expect("unreachable"),
+                ToggleWidget::new(options.grid),
+            ),
+            rows_as_layers: BlockWidget::new(
+                "LAYERS".parse().expect("unreachable"),
+                ToggleWidget::new(options.rows_as_layers),
+            ),
+            import: ButtonWidget::new(ButtonKind::Basic, IconId::Import),
+            requested: false,
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested
+    }
+}
+
+impl Widget for SpriteSheetWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        self.size.render_if_need(app, canvas);
+        self.grid.render_if_need(app, canvas);
+        self.rows_as_layers.render_if_need(app, canvas);
+        self.import.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        self.size.handle_event(app, event).or_fail()?;
+        app.sprite_sheet_options.size = self.size.body().value();
+
+        self.grid.handle_event(app, event).or_fail()?;
+        app.sprite_sheet_options.grid = self.grid.body().is_on();
+
+        self.rows_as_layers.handle_event(app, event).or_fail()?;
+        app.sprite_sheet_options.rows_as_layers = self.rows_as_layers.body().is_on();
+
+        self.import.handle_event(app, event).or_fail()?;
+        if self.import.take_clicked(app) {
+            app.enqueue_io_request(IoRequest::ImportSpriteSheet);
+            self.requested = true;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        vec![
+            &mut self.size,
+            &mut self.grid,
+            &mut self.rows_as_layers,
+            &mut self.import,
+        ]
+    }
+}
+
+impl FixedSizeWidget for SpriteSheetWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let mut size = self.size.requiring_size(app);
+        size.width += MARGIN + self.grid.requiring_size(app).width;
+        size.width += MARGIN + self.rows_as_layers.requiring_size(app).width;
+        size.width += MARGIN + self.import.requiring_size(app).width;
+        size.height = size.height.max(self.import.requiring_size(app).height);
+        size + MARGIN * 2
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+        let region = self.region.without_margin(MARGIN);
+
+        let mut size_region = region;
+        size_region.size = self.size.requiring_size(app);
+        self.size.set_region(app, size_region);
+
+        let mut grid_region = region;
+        grid_region.position.x = size_region.end().x + MARGIN as i32;
+        grid_region.size = self.grid.requiring_size(app);
+        self.grid.set_region(app, grid_region);
+
+        let mut rows_as_layers_region = region;
+        rows_as_layers_region.position.x = grid_region.end().x + MARGIN as i32;
+        rows_as_layers_region.size = self.rows_as_layers.requiring_size(app);
+        self.rows_as_layers.set_region(app, rows_as_layers_region);
+
+        let mut import_position = region.position;
+        import_position.x = rows_as_layers_region.end().x + MARGIN as i32;
+        self.import.set_position(app, import_position);
+    }
+}
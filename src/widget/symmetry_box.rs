@@ -0,0 +1,134 @@
+use super::{FixedSizeWidget, Widget, button::ButtonWidget};
+use crate::{
+    app::App,
+    asset::{ButtonKind, IconId},
+    canvas_ext::CanvasExt,
+    color,
+    event::Event,
+    model::tool::{SymmetryMode, SymmetryState, ToolKind},
+    pixel::PixelRegion,
+};
+use orfail::{OrFail, Result};
+use pagurus::image::Canvas;
+use pagurus::spatial::{Position, Region, Size};
+
+const MARGIN: u32 = 8;
+
+#[derive(Debug)]
+pub struct SymmetryBoxWidget {
+    region: Region,
+    mode: ButtonWidget,
+    axis: ButtonWidget,
+    current: SymmetryMode,
+    visible: bool,
+}
+
+impl SymmetryBoxWidget {
+    // Moves the axes to the center of the selected area (or resets them to the frame center)
+    fn update_axis(app: &mut App) {
+        let models = app.models();
+        let axis = if models.selection.is_empty() {
+            None
+        } else {
+            let frame = models.config.frame.get_base_region();
+            let region = PixelRegion::from_positions(models.selection.iter().copied());
+            Some(SymmetryState::region_axis(frame, region))
+        };
+        app.models_mut().tool.symmetry.axis = axis;
+    }
+}
+
+impl Default for SymmetryBoxWidget {
+    fn default() -> Self {
+        Self {
+            region: Default::default(),
+            mode: ButtonWidget::new(ButtonKind::Basic, SymmetryMode::default().icon()),
+            axis: ButtonWidget::new(ButtonKind::Basic, IconId::SymmetryAxis)
+                .with_disabled_callback(|app| {
+                    app.models().tool.symmetry.mode == SymmetryMode::None
+                }),
+            current: SymmetryMode::default(),
+            visible: false,
+        }
+    }
+}
+
+impl Widget for SymmetryBoxWidget {
+    fn region(&self) -> Region {
+        self.region
+    }
+
+    fn render(&self, app: &App, canvas: &mut Canvas) {
+        if !self.visible {
+            return;
+        }
+        canvas.fill_rectangle(self.region, color::BUTTONS_BACKGROUND);
+        canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
+        self.mode.render_if_need(app, canvas);
+        self.axis.render_if_need(app, canvas);
+    }
+
+    fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.mode.handle_event(app, event).or_fail()?;
+        if self.mode.take_clicked(app) {
+            let symmetry = &mut app.models_mut().tool.symmetry;
+            symmetry.mode = symmetry.mode.next();
+        }
+        self.axis.handle_event(app, event).or_fail()?;
+        if self.axis.take_clicked(app) {
+            Self::update_axis(app);
+        }
+        event.consume_if_contained(self.region);
+        Ok(())
+    }
+
+    fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
+        let visible = matches!(
+            app.models().tool.current,
+            ToolKind::Draw | ToolKind::Erase | ToolKind::Fill | ToolKind::Gradient
+        );
+        if self.visible != visible {
+            self.visible = visible;
+            app.request_redraw(self.region);
+        }
+
+        let mode = app.models().tool.symmetry.mode;
+        if self.current != mode {
+            self.current = mode;
+            self.mode.set_icon(app, mode.icon());
+            app.request_redraw(self.axis.region());
+        }
+
+        for child in self.children() {
+            child.handle_event_after(app).or_fail()?;
+        }
+        Ok(())
+    }
+
+    fn children(&mut self) -> Vec<&mut dyn Widget> {
+        vec![&mut self.mode, &mut self.axis]
+    }
+}
+
+impl FixedSizeWidget for SymmetryBoxWidget {
+    fn requiring_size(&self, app: &App) -> Size {
+        let mode = self.mode.requiring_size(app);
+        let axis = self.axis.requiring_size(app);
+        Size::from_wh(
+            mode.width + MARGIN + axis.width,
+            mode.height.max(axis.height),
+        ) + (MARGIN * 2)
+    }
+
+    fn set_position(&mut self, app: &App, position: Position) {
+        self.region = Region::new(position, self.requiring_size(app));
+
+        let offset = position + MARGIN as i32;
+        self.mode.set_position(app, offset);
+        let x = (self.mode.requiring_size(app).width + MARGIN) as i32;
+        self.axis.set_position(app, offset.move_x(x));
+    }
+}
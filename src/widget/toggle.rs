@@ -34,6 +34,13 @@ impl ToggleWidget {
     pub fn is_on(&self) -> bool {
         matches!(self.state, ToggleState::OnNeutral | ToggleState::OnFocused)
     }
+
+    pub fn set_on(&mut self, app: &mut App, on: bool) {
+        if self.is_on() != on {
+            self.state.toggle();
+            app.request_redraw(self.region);
+        }
+    }
 }
 
 impl Widget for ToggleWidget {
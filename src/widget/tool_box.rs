@@ -55,9 +55,10 @@ impl ToolBoxWidget {
             Key::Char('p') => 0, // ToolKind::Pick
             Key::Char('d') => 1, // ToolKind::Draw
             Key::Char('f') => 2, // ToolKind::Fill
-            Key::Char('e') => 3, // ToolKind::Erase
-            Key::Char('s') => 4, // ToolKind::Select
-            Key::Char('m') => 5, // ToolKind::Move
+            Key::Char('g') => 3, // ToolKind::Gradient
+            Key::Char('e') => 4, // ToolKind::Erase
+            Key::Char('s') => 5, // ToolKind::Select
+            Key::Char('m') => 6, // ToolKind::Move
             _ => {
                 return Ok(false);
             }
@@ -74,6 +75,7 @@ impl Default for ToolBoxWidget {
             ButtonWidget::new(ButtonKind::Basic, IconId::Pick),
             ButtonWidget::new(ButtonKind::Basic, IconId::PenStroke),
             ButtonWidget::new(ButtonKind::Basic, IconId::Bucket),
+            ButtonWidget::new(ButtonKind::Basic, IconId::Gradient),
             ButtonWidget::new(ButtonKind::Basic, IconId::Erase),
             ButtonWidget::new(ButtonKind::Basic, IconId::Lasso),
             ButtonWidget::new(ButtonKind::Basic, IconId::Move),
@@ -82,9 +84,10 @@ impl Default for ToolBoxWidget {
         buttons[0].set_disabled_callback(|app| app.models().tool.current == ToolKind::Pick);
         buttons[1].set_disabled_callback(|app| app.models().tool.current == ToolKind::Draw);
         buttons[2].set_disabled_callback(|app| app.models().tool.current == ToolKind::Fill);
-        buttons[3].set_disabled_callback(|app| app.models().tool.current == ToolKind::Erase);
-        buttons[4].set_disabled_callback(|app| app.models().tool.current == ToolKind::Select);
-        buttons[5].set_disabled_callback(|app| app.models().tool.current == ToolKind::Move);
+        buttons[3].set_disabled_callback(|app| app.models().tool.current == ToolKind::Gradient);
+        buttons[4].set_disabled_callback(|app| app.models().tool.current == ToolKind::Erase);
+        buttons[5].set_disabled_callback(|app| app.models().tool.current == ToolKind::Select);
+        buttons[6].set_disabled_callback(|app| app.models().tool.current == ToolKind::Move);
 
         Self {
             region: Default::default(),
@@ -7,19 +7,21 @@ use crate::{
     event::Event,
     pixel::PixelRegion,
     region_ext::RegionExt,
+    window::history::HistoryWindow,
 };
 use orfail::{OrFail, Result};
 use pagurus::spatial::{Position, Region, Size};
 use pagurus::{event::Key, image::Canvas};
 
 const MARGIN: u32 = 8;
-const MAX_UNDO: usize = 100;
+const MAX_UNDO_MEMORY_BYTES: usize = 64 * 1024 * 1024;
 
 #[derive(Debug)]
 pub struct UndoRedoWidget {
     region: Region,
     undo: ButtonWidget,
     redo: ButtonWidget,
+    history: ButtonWidget,
 }
 
 impl UndoRedoWidget {
@@ -36,17 +38,17 @@ impl UndoRedoWidget {
         };
         match event.key {
             Key::Char('z') if event.ctrl => {
-                let config = app.models().config.clone();
-                app.models_mut()
+                let models = app.models_mut();
+                models
                     .pixel_canvas
-                    .undo_command(&config)
+                    .undo_command(&mut models.config)
                     .or_fail()?;
             }
             Key::Char('y') if event.ctrl => {
-                let config = app.models().config.clone();
-                app.models_mut()
+                let models = app.models_mut();
+                models
                     .pixel_canvas
-                    .redo_command(&config)
+                    .redo_command(&mut models.config)
                     .or_fail()?;
             }
             _ => {
@@ -74,10 +76,13 @@ impl Default for UndoRedoWidget {
         let mut undo = ButtonWidget::new(ButtonKind::Basic, IconId::Undo);
         undo.set_disabled_callback(|app| app.models().pixel_canvas.command_log_tail() == 0);
         undo.set_number_callback(0, |app| app.models().pixel_canvas.command_log_tail() as u32);
+        let history = ButtonWidget::new(ButtonKind::Basic, IconId::History)
+            .with_disabled_callback(|app| app.models().pixel_canvas.command_log().is_empty());
         Self {
             region: Default::default(),
             undo,
             redo,
+            history,
         }
     }
 }
@@ -92,6 +97,7 @@ impl Widget for UndoRedoWidget {
         canvas.draw_rectangle(self.region, color::WINDOW_BORDER);
         self.redo.render_if_need(app, canvas);
         self.undo.render_if_need(app, canvas);
+        self.history.render_if_need(app, canvas);
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
@@ -101,33 +107,38 @@ impl Widget for UndoRedoWidget {
 
         self.redo.handle_event(app, event).or_fail()?;
         if self.redo.take_clicked(app) {
-            let config = app.models().config.clone();
-            app.models_mut()
+            let models = app.models_mut();
+            models
                 .pixel_canvas
-                .redo_command(&config)
+                .redo_command(&mut models.config)
                 .or_fail()?;
             self.request_redraw_dirty_canvas_region(app);
         }
 
         self.undo.handle_event(app, event).or_fail()?;
         if self.undo.take_clicked(app) {
-            let config = app.models().config.clone();
-            app.models_mut()
+            let models = app.models_mut();
+            models
                 .pixel_canvas
-                .undo_command(&config)
+                .undo_command(&mut models.config)
                 .or_fail()?;
             self.request_redraw_dirty_canvas_region(app);
         }
 
+        self.history.handle_event(app, event).or_fail()?;
+        if self.history.take_clicked(app) {
+            app.spawn_window(HistoryWindow::new(app)).or_fail()?;
+        }
+
         event.consume_if_contained(self.region);
         Ok(())
     }
 
     fn handle_event_after(&mut self, app: &mut App) -> Result<()> {
-        let max_undo = MAX_UNDO;
-        while app.models().pixel_canvas.command_log_tail() > max_undo {
-            app.models_mut().pixel_canvas.forget_oldest_command();
-        }
+        let max_undos = app.models().config.max_undos.get() as usize;
+        app.models_mut()
+            .pixel_canvas
+            .enforce_command_log_budget(max_undos, MAX_UNDO_MEMORY_BYTES);
         for child in self.children() {
             child.handle_event_after(app).or_fail()?;
         }
@@ -135,7 +146,7 @@ impl Widget for UndoRedoWidget {
     }
 
     fn children(&mut self) -> Vec<&mut dyn Widget> {
-        vec![&mut self.redo, &mut self.undo]
+        vec![&mut self.redo, &mut self.undo, &mut self.history]
     }
 }
 
@@ -143,9 +154,10 @@ impl FixedSizeWidget for UndoRedoWidget {
     fn requiring_size(&self, app: &App) -> Size {
         let undo_size = self.undo.requiring_size(app);
         let redo_size = self.redo.requiring_size(app);
+        let history_size = self.history.requiring_size(app);
         Size::from_wh(
             redo_size.width + MARGIN * 2,
-            redo_size.height + undo_size.height + MARGIN * 4,
+            redo_size.height + undo_size.height + history_size.height + MARGIN * 6,
         )
     }
 
@@ -153,11 +165,13 @@ impl FixedSizeWidget for UndoRedoWidget {
         self.region = Region::new(position, self.requiring_size(app));
 
         let mut block = self.region;
-        block.size.height /= 2;
+        block.size.height /= 3;
 
         self.redo
             .set_position(app, block.without_margin(MARGIN).position);
         self.undo
             .set_position(app, block.shift_y(1).without_margin(MARGIN).position);
+        self.history
+            .set_position(app, block.shift_y(2).without_margin(MARGIN).position);
     }
 }
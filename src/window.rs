@@ -4,7 +4,9 @@ use pagurus::{Result, spatial::Region};
 
 pub mod color_selector;
 pub mod config;
+pub mod history;
 pub mod main;
+pub mod sprite_sheet;
 pub mod widget;
 
 pub trait Window: 'static + std::fmt::Debug {
@@ -1,5 +1,9 @@
 use super::{Window, widget::WidgetWindow};
-use crate::{app::App, event::Event, widget::color_selector::ColorSelectorWidget};
+use crate::{
+    app::App,
+    event::Event,
+    widget::{FixedSizeWidget, color_selector::ColorSelectorWidget},
+};
 use orfail::{OrFail, Result};
 use pagurus::image::Canvas;
 use pagurus::spatial::Region;
@@ -31,6 +35,13 @@ impl Window for ColorSelectorWindow {
     }
 
     fn handle_event(&mut self, app: &mut App, event: &mut Event) -> Result<()> {
-        self.0.handle_event(app, event).or_fail()
+        let size = self.0.widget().requiring_size(app);
+        self.0.handle_event(app, event).or_fail()?;
+        if size != self.0.widget().requiring_size(app) {
+            app.request_redraw(self.0.region());
+            self.0.handle_screen_resized(app).or_fail()?;
+            app.request_redraw(self.0.region());
+        }
+        Ok(())
     }
 }
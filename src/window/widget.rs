@@ -32,6 +32,10 @@ impl<W: FixedSizeWidget> WidgetWindow<W> {
             margin,
         }
     }
+
+    pub fn widget(&self) -> &W {
+        &self.widget
+    }
 }
 
 impl<W: FixedSizeWidget> Window for WidgetWindow<W> {
@@ -196,6 +196,9 @@ class App {
 
       type RequestJson = "saveWorkspace"
           | "loadWorkspace"
+          | "importSpriteSheet"
+          | "importPalette"
+          | "exportPalette"
           | { inputNumber: { id: number } }
           | { inputSize: { id: number } }
           | "vibrate";
@@ -210,6 +213,15 @@ class App {
         case "loadWorkspace":
           this.loadWorkspace();
           break;
+        case "importSpriteSheet":
+          this.loadFile("importSpriteSheet", "image/png", "Failed to import sprite sheet");
+          break;
+        case "importPalette":
+          this.loadFile("importPalette", ".gpl,.pal,.txt,.hex", "Failed to import palette file");
+          break;
+        case "exportPalette":
+          this.exportPalette();
+          break;
         case "vibrate":
           if ("vibrate" in window.navigator) {
             window.navigator.vibrate(50);
@@ -243,10 +255,24 @@ class App {
     element.click();
   }
 
+  private exportPalette() {
+    const data = this.game.query(this.system, "paletteGpl");
+    const blob = new Blob([data], { type: "text/plain" });
+    const element = document.createElement("a");
+    element.download = "palette.gpl";
+    element.href = URL.createObjectURL(blob);
+
+    element.click();
+  }
+
   private loadWorkspace() {
+    this.loadFile("loadWorkspace", "image/png", "Failed to load workspace file");
+  }
+
+  private loadFile(command: string, accept: string, errorMessage: string) {
     const input = document.createElement("input");
     input.setAttribute("type", "file");
-    input.setAttribute("accept", "image/png");
+    input.setAttribute("accept", accept);
 
     // [NOTE] This is necessary to trigger the onchange event in Safari.
     document.body.appendChild(input);
@@ -263,10 +289,10 @@ class App {
 
       const data = new Uint8Array(await file.arrayBuffer());
       try {
-        this.game.command(this.system, "loadWorkspace", data);
+        this.game.command(this.system, command, data);
       } catch (e) {
         console.warn(e);
-        alert("Failed to load workspace file");
+        alert(errorMessage);
       }
     };
     input.oncancel = async () => {